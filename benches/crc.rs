@@ -0,0 +1,34 @@
+//! Chunk crc throughput, from tiny metadata chunks to image data chunks of
+//! hundreds of megabytes.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use pngme::checksum::Crc;
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+
+const SIZES: [usize; 6] = [16, 1 << 10, 64 << 10, 1 << 20, 16 << 20, 256 << 20];
+
+fn crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let data = vec![0xa5; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("checksum", size), &data, |b, data| {
+            b.iter(|| Crc::checksum(&ChunkType::IDAT, data))
+        });
+        group.bench_with_input(BenchmarkId::new("chunk_new", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |data| Chunk::new(ChunkType::IDAT, data),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, crc);
+criterion_main!(benches);
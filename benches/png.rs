@@ -0,0 +1,130 @@
+//! Parsing, serialising and embedding over generated files with varying
+//! chunk counts and sizes.
+
+use std::str::FromStr;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::chunks::ihdr::{ColorType, Ihdr};
+use pngme::png::{Png, PngRef};
+
+/// (number of IDAT chunks, bytes per chunk)
+const SHAPES: [(usize, usize); 5] = [
+    (1, 1 << 10),
+    (1, 1 << 20),
+    (100, 1 << 10),
+    (100, 64 << 10),
+    (10_000, 100),
+];
+
+fn generated_png(count: usize, size: usize) -> Png {
+    let ihdr = Ihdr::new(1024, 1024, 8, ColorType::Rgb);
+    let mut chunks = vec![ihdr.to_chunk()];
+    chunks.extend((0..count).map(|index| Chunk::new(ChunkType::IDAT, vec![index as u8; size])));
+    chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+    Png::from_chunks(chunks)
+}
+
+fn label(count: usize, size: usize) -> String {
+    format!("{count}x{size}")
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(20);
+    for (count, size) in SHAPES {
+        let bytes = generated_png(count, size).as_bytes();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("Png::try_from", label(count, size)),
+            &bytes,
+            |b, bytes| b.iter(|| Png::try_from(bytes.as_slice()).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("PngRef::try_from", label(count, size)),
+            &bytes,
+            |b, bytes| b.iter(|| PngRef::try_from(bytes.as_slice()).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn serialise(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialise");
+    group.sample_size(20);
+    for (count, size) in SHAPES {
+        let png = generated_png(count, size);
+        group.throughput(Throughput::Bytes((count * (size + 12)) as u64));
+        group.bench_with_input(
+            BenchmarkId::new("Png::as_bytes", label(count, size)),
+            &png,
+            |b, png| b.iter(|| png.as_bytes()),
+        );
+    }
+    group.finish();
+}
+
+fn chunks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk");
+    for size in [16, 1 << 10, 1 << 20] {
+        let data = vec![0x5a; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("Chunk::new", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |data| Chunk::new(ChunkType::IDAT, data),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    for (count, size) in SHAPES {
+        let mut png = generated_png(count, size);
+        // the chunk looked up is the last one, so every lookup scans the file
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"needle".to_vec(),
+        ));
+        group.throughput(Throughput::Elements(png.chunks().len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("chunk_by_type", label(count, size)),
+            &png,
+            |b, png| b.iter(|| png.chunk_by_type("ruSt").unwrap().length()),
+        );
+    }
+    group.finish();
+}
+
+/// What `pngme encode` followed by `pngme decode` does, without the file
+/// system.
+fn round_trip(c: &mut Criterion) {
+    let chunk_type = ChunkType::from_str("ruSt").unwrap();
+    let mut group = c.benchmark_group("round_trip");
+    group.sample_size(20);
+    for (count, size) in SHAPES {
+        let bytes = generated_png(count, size).as_bytes();
+        for message_size in [16, 64 << 10] {
+            let message = vec![b'm'; message_size];
+            group.throughput(Throughput::Bytes(bytes.len() as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("encode_decode/{message_size}"), label(count, size)),
+                &bytes,
+                |b, bytes| {
+                    b.iter(|| {
+                        let mut png = Png::try_from(bytes.as_slice()).unwrap();
+                        png.append_chunk(Chunk::new(chunk_type, message.clone()));
+                        let encoded = png.as_bytes();
+
+                        let png = PngRef::try_from(encoded.as_slice()).unwrap();
+                        png.chunk_by_type("ruSt").unwrap().data().len()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, serialise, chunks, round_trip);
+criterion_main!(benches);
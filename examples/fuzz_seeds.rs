@@ -0,0 +1,148 @@
+//! Writes the seed corpus for the fuzz targets in `fuzz/`:
+//!
+//!     cargo run --example fuzz_seeds -- fuzz/seeds
+//!
+//! Every seed is a small but complete file, so fuzzing starts out with
+//! coverage of each chunk type the crate decodes.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::chunks::apng;
+use pngme::chunks::color::{Chromaticities, Gamma, RenderingIntent};
+use pngme::chunks::exif::{Exif, Ifd, Value};
+use pngme::chunks::ihdr::{ColorType, Ihdr};
+use pngme::chunks::palette::{Palette, Transparency};
+use pngme::chunks::phys::Phys;
+use pngme::chunks::text::Text;
+use pngme::chunks::time::Time;
+use pngme::chunks::xmp::Xmp;
+use pngme::png::Png;
+use pngme::registry;
+use pngme::Result;
+
+fn deflate(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn image(color_type: ColorType, bit_depth: u8, interlace_method: u8) -> (Ihdr, Chunk) {
+    let ihdr = Ihdr {
+        interlace_method,
+        ..Ihdr::new(4, 3, bit_depth, color_type)
+    };
+    let idat = Chunk::new(
+        ChunkType::IDAT,
+        deflate(&vec![0; ihdr.image_data_len() as usize]),
+    );
+    (ihdr, idat)
+}
+
+fn iend() -> Chunk {
+    Chunk::new(ChunkType::IEND, Vec::new())
+}
+
+fn seeds() -> Result<Vec<(&'static str, Png)>> {
+    let (ihdr, idat) = image(ColorType::Grayscale, 8, 0);
+    let greyscale = Png::from_chunks(vec![ihdr.to_chunk(), idat, iend()]);
+
+    let (ihdr, idat) = image(ColorType::Indexed, 2, 0);
+    let palette = Palette::new(vec![[0, 0, 0], [255, 0, 0], [0, 255, 0]])?;
+    let transparency = Transparency::Indexed(vec![0, 128]);
+    let indexed = Png::from_chunks(vec![
+        ihdr.to_chunk(),
+        palette.to_chunk(),
+        transparency.to_chunk(),
+        idat,
+        iend(),
+    ]);
+
+    let (ihdr, idat) = image(ColorType::Rgba, 16, 1);
+    let mut exif = Exif::default();
+    exif.set_text(Ifd::Primary, 0x010f, "pngme")?;
+    exif.set(Ifd::Primary, 0x0112, Value::Short(vec![1]));
+    let mut compressed = Text::new("Comment", "zlib compressed");
+    compressed.compressed = true;
+    let mut international = Text::new("Title", "Ünïcödé");
+    international.international = Some(Default::default());
+    let metadata = Png::from_chunks(vec![
+        ihdr.to_chunk(),
+        Gamma(45455).to_chunk(),
+        Chromaticities {
+            white: (31270, 32900),
+            red: (64000, 33000),
+            green: (30000, 60000),
+            blue: (15000, 6000),
+        }
+        .to_chunk(),
+        RenderingIntent::Perceptual.to_chunk(),
+        Phys::from_dpi(72)?.to_chunk(),
+        exif.to_chunk(),
+        idat,
+        Text::new("Author", "pngme").to_chunk()?,
+        compressed.to_chunk()?,
+        international.to_chunk()?,
+        Xmp::parse(r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#)?.to_chunk(),
+        Time::new(2024, 2, 29, 12, 0, 0)?.to_chunk(),
+        iend(),
+    ]);
+
+    let animated = apng::assemble(&[greyscale.clone(), greyscale.clone()], (1, 10), 0)?;
+
+    let mut trailer = greyscale.clone();
+    trailer.set_trailer(b"hidden after IEND".to_vec());
+
+    Ok(vec![
+        ("greyscale", greyscale),
+        ("indexed", indexed),
+        ("metadata", metadata),
+        ("animated", animated),
+        ("trailer", trailer),
+    ])
+}
+
+fn write(dir: &Path, name: &str, bytes: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(name), bytes)?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let out = std::env::args()
+        .nth(1)
+        .ok_or("usage: fuzz_seeds <output directory>")?;
+    let out = Path::new(&out);
+
+    let mut infos: Vec<_> = registry::standard().iter().collect();
+    infos.sort_by_key(|info| info.chunk_type.bytes());
+
+    for (name, png) in seeds()? {
+        let bytes = png.as_bytes();
+        write(&out.join("png"), &format!("{name}.png"), &bytes)?;
+        write(&out.join("check"), &format!("{name}.png"), &bytes)?;
+
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            let chunk_type = chunk.chunk_type();
+            let file_name = format!("{name}-{index}-{chunk_type}");
+            write(&out.join("chunk"), &file_name, &chunk.as_bytes())?;
+            write(
+                &out.join("chunk_type"),
+                &chunk_type.to_string(),
+                chunk_type.to_string().as_bytes(),
+            )?;
+
+            // the decoders target picks the chunk type with its first byte
+            if let Some(selector) = infos.iter().position(|info| &info.chunk_type == chunk_type) {
+                let data = [&[selector as u8], chunk.data()].concat();
+                write(&out.join("decoders"), &file_name, &data)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::{check, repair};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = check::check(data).to_string();
+    if let Ok(repaired) = repair::repair(data) {
+        let _ = repaired.png.as_bytes();
+        for fix in &repaired.fixes {
+            let _ = fix.to_string();
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk::{Chunk, ChunkRef};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ChunkRef::read(data);
+    let _ = Chunk::try_from_lenient(data);
+    let _ = Chunk::from_file_bytes(data);
+
+    if let Ok(chunk) = Chunk::try_from(data) {
+        assert_eq!(chunk.as_bytes(), data);
+        assert!(chunk.has_valid_crc());
+    }
+});
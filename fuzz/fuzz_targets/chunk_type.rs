@@ -0,0 +1,21 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk_type::ChunkType;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(name) = std::str::from_utf8(data) else {
+        return;
+    };
+    let from_str = ChunkType::from_str(name);
+    if let Ok(bytes) = <[u8; 4]>::try_from(data) {
+        // both constructors accept exactly the same chunk types
+        assert_eq!(from_str, ChunkType::try_from(bytes));
+    }
+    if let Ok(chunk_type) = from_str {
+        assert_eq!(chunk_type.to_string(), name);
+        let _ = chunk_type.validate();
+    }
+});
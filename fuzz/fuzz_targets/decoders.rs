@@ -0,0 +1,35 @@
+#![no_main]
+
+//! Feeds arbitrary data to the decoder of every known chunk type. The first
+//! byte picks the chunk type.
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk::{Chunk, ChunkRef};
+use pngme::chunks::{exif::Exif, text::Text, xmp::Xmp};
+use pngme::registry;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, data)) = data.split_first() else {
+        return;
+    };
+    let mut infos: Vec<_> = registry::standard().iter().collect();
+    infos.sort_by_key(|info| info.chunk_type.bytes());
+    let info = infos[selector as usize % infos.len()];
+
+    let chunk = Chunk::new(info.chunk_type, data.to_vec());
+    let _ = registry::standard().describe(&ChunkRef::from(&chunk));
+    let _ = info.validate(&ChunkRef::from(&chunk));
+
+    if let Ok(text) = Text::try_from(&chunk) {
+        let _ = text.to_chunk();
+    }
+    if let Ok(exif) = Exif::parse(data) {
+        let _ = exif.to_string();
+        assert!(Exif::parse(&exif.to_bytes()).is_ok());
+    }
+    if let Ok(xml) = std::str::from_utf8(data) {
+        if let Ok(xmp) = Xmp::parse(xml) {
+            let _ = xmp.to_string();
+        }
+    }
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunks::color::ColorSpace;
+use pngme::png::{Png, PngRef};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(png) = PngRef::try_from_lenient(data) {
+        let _ = png.to_string();
+    }
+    let _ = Png::try_from_lenient(data);
+
+    let Ok(png) = Png::try_from(data) else {
+        return;
+    };
+    // everything up to IEND and the trailer after it survive a round trip
+    assert_eq!(png.as_bytes(), data);
+
+    let _ = png.ihdr();
+    let _ = png.palette();
+    let _ = png.transparency();
+    let _ = png.frames();
+    let _ = ColorSpace::of(&png);
+});
@@ -1,10 +1,10 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(about = "A CLI to encode and decode information from and into PNG files. Made by following this project tutorial -> https://picklenerd.github.io/pngme_book/introduction.html", long_about = None)]
 #[command(author = "S460")]
 #[command(version = "1.0")]
-pub struct CLI {
+pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -21,9 +21,15 @@ pub enum Commands {
         #[arg(short, long)]
         file_path: std::path::PathBuf,
 
-        /// type of the chunk to encode
+        /// type of the chunk to encode, required for the chunk method and
+        /// not allowed with the trailer method
         #[arg(short, long)]
-        chunk_type: String,
+        chunk_type: Option<String>,
+
+        /// where to hide the message; a trailer replaces any data already
+        /// stored after IEND
+        #[arg(long, value_enum, default_value_t = Method::Chunk)]
+        method: Method,
         // add output file later on
     },
     /// decode message from file
@@ -32,9 +38,14 @@ pub enum Commands {
         #[arg(short, long)]
         file_path: std::path::PathBuf,
 
-        /// type of the chunk to decode
+        /// type of the chunk to decode, required for the chunk method and
+        /// not allowed with the trailer method
         #[arg(short, long)]
-        chunk_type: String,
+        chunk_type: Option<String>,
+
+        /// where the message is hidden
+        #[arg(long, value_enum, default_value_t = Method::Chunk)]
+        method: Method,
     },
 
     /// remove chunk with specified chunk type
@@ -46,14 +57,362 @@ pub enum Commands {
         /// type of the chunk to remove
         #[arg(short, long)]
         chunk_type: String,
+
+        /// keep unknown unsafe-to-copy chunks even if image data changes
+        #[arg(long)]
+        keep_unsafe: bool,
     },
 
     /// print the file
+    #[command(alias = "info")]
     Print {
         /// path to the PNG file to print
         #[arg(short, long)]
         file_path: std::path::PathBuf,
+
+        /// show chunk properties, offsets and decoded contents
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// keep going when chunks have invalid crcs
+        #[arg(short, long)]
+        lenient: bool,
+
+        /// output format; json always includes everything `--verbose` shows
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// print a hex dump of a chunk's data
+    Dump {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// type of the chunk to dump
+        #[arg(short, long, alias = "type")]
+        chunk_type: String,
+
+        /// which chunk of that type to dump, counting from 0
+        #[arg(short, long, default_value_t = 0)]
+        index: usize,
+    },
+
+    /// write a chunk out to a separate file
+    Extract {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// type of the chunk to extract
+        #[arg(short, long, alias = "type")]
+        chunk_type: String,
+
+        /// which chunk of that type to extract, counting from 0
+        #[arg(short, long, default_value_t = 0)]
+        index: usize,
+
+        /// path of the file to write
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+
+        /// what to write to the output file
+        #[arg(short, long, value_enum, default_value_t = ExtractMode::Data)]
+        mode: ExtractMode,
+    },
+
+    /// insert a chunk read from a chunk file
+    Inject {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// chunk file written by `extract --mode chunk` (or a raw chunk)
+        #[arg(short = 'n', long)]
+        input: std::path::PathBuf,
+
+        /// position to insert the chunk at, defaults to just before IEND
+        #[arg(short, long)]
+        index: Option<usize>,
+
+        /// keep unknown unsafe-to-copy chunks even if image data changes
+        #[arg(long)]
+        keep_unsafe: bool,
+    },
+
+    /// validate the file and report every problem found
+    ///
+    /// Exits with 0 for a clean file, 1 if there are only warnings and 2 if
+    /// there are errors. Exits with 3 if the file cannot be read.
+    Check {
+        /// path to the PNG file to check
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+    },
+
+    /// compare the chunks of two PNG files
+    ///
+    /// Lists added, removed, moved and modified chunks. Exits with 0 when
+    /// the files match, 1 when they differ and 2 if either file cannot be
+    /// read.
+    Diff {
+        /// the original PNG file
+        old: std::path::PathBuf,
+
+        /// the PNG file to compare it with
+        new: std::path::PathBuf,
+
+        /// chunk types to leave out of the comparison, such as `tIME,zTXt`
+        #[arg(short, long, value_delimiter = ',')]
+        ignore: Vec<String>,
+    },
+
+    /// fix wrong crcs, broken lengths, trailing garbage and a missing IEND
+    Repair {
+        /// path to the PNG file to repair
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// where to write the repaired file, defaults to overwriting the input
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+
+        /// also apply fixes that change critical chunks, which are otherwise
+        /// only reported
+        #[arg(long)]
+        force: bool,
+
+        /// only report the fixes, don't write anything
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// remove ancillary chunks and other data from the file
+    Strip {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// which ancillary chunks to remove
+        #[arg(short, long, value_enum)]
+        policy: Option<StripPolicy>,
+
+        /// chunk types to always keep, such as `pHYs,sRGB`
+        #[arg(short, long, value_delimiter = ',')]
+        keep: Vec<String>,
+
+        /// chunk types to remove regardless of the policy
+        #[arg(short, long, value_delimiter = ',')]
+        drop: Vec<String>,
+
+        /// remove any data stored after IEND
+        #[arg(long)]
+        trailer: bool,
+
+        /// only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
     },
+
+    /// show the colour space, or strip or replace the colour profile
+    Color {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// remove every colour space chunk: gAMA, cHRM, sRGB, iCCP, cICP and
+        /// also the mDCv and cLLi HDR metadata
+        #[arg(long, conflicts_with = "icc")]
+        strip: bool,
+
+        /// embed this ICC profile, replacing any sRGB, iCCP or cICP chunk
+        #[arg(long)]
+        icc: Option<std::path::PathBuf>,
+
+        /// name to store with the ICC profile
+        #[arg(long, default_value = "ICC profile")]
+        name: String,
+    },
+
+    /// set the modification time stored in the tIME chunk
+    Touch {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// UTC time as YYYY-MM-DD HH:MM:SS, defaults to now
+        #[arg(short, long)]
+        time: Option<String>,
+    },
+
+    /// show or set the resolution stored in the pHYs chunk
+    Dpi {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// resolution in dots per inch to write
+        #[arg(long)]
+        set: Option<u32>,
+    },
+
+    /// list, edit or remove EXIF tags
+    Exif {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// set a tag, such as `Orientation=1`
+        #[arg(long, value_name = "TAG=VALUE")]
+        set: Vec<String>,
+
+        /// remove a tag by name
+        #[arg(long, value_name = "TAG")]
+        remove: Vec<String>,
+
+        /// remove all GPS location tags
+        #[arg(long)]
+        strip_gps: bool,
+    },
+
+    /// read or change the XMP metadata packet
+    Xmp {
+        #[command(subcommand)]
+        command: XmpCommands,
+    },
+
+    /// work with animated PNGs
+    Apng {
+        #[command(subcommand)]
+        command: ApngCommands,
+    },
+
+    /// assemble a PNG file from a TOML or JSON manifest of chunks
+    ///
+    /// Lengths and crcs are computed; data can be given as hex, base64, a
+    /// file or, for IHDR, text, pHYs and tIME chunks, as fields. Warns when
+    /// the chunks do not start with IHDR and end with IEND.
+    #[cfg(feature = "manifest")]
+    Build {
+        /// manifest to read, as JSON if it ends in `.json` and TOML otherwise
+        manifest: std::path::PathBuf,
+
+        /// path of the PNG file to write
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+
     /// show an awesome banner
     Banner,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum ApngCommands {
+    /// list the frames of an animated PNG
+    List {
+        /// path to the animated PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+    },
+
+    /// write the region of a single frame out as a standalone PNG
+    Extract {
+        /// path to the animated PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// frame to extract, counting from 0
+        #[arg(short, long)]
+        index: usize,
+
+        /// path of the PNG file to write
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+    },
+
+    /// combine PNG files into an animated PNG
+    Assemble {
+        /// path of the animated PNG file to write
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+
+        /// delay between frames in milliseconds
+        #[arg(short, long, default_value_t = 100)]
+        delay_ms: u16,
+
+        /// how many times to play the animation, 0 loops forever
+        #[arg(short, long, default_value_t = 0)]
+        plays: u32,
+
+        /// PNG files to use as frames, in order
+        #[arg(required = true)]
+        frames: Vec<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum XmpCommands {
+    /// pretty-print the XMP packet
+    Get {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+    },
+
+    /// store an XMP packet, replacing the existing one
+    Set {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+
+        /// path to the XMP packet to store
+        #[arg(short = 'n', long)]
+        input: std::path::PathBuf,
+
+        /// merge the properties into the existing packet instead
+        #[arg(short, long)]
+        merge: bool,
+    },
+
+    /// remove the XMP packet
+    Remove {
+        /// path to the PNG file
+        #[arg(short, long)]
+        file_path: std::path::PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// in a chunk of its own
+    Chunk,
+    /// after the IEND chunk
+    Trailer,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// human readable text
+    Text,
+    /// a JSON object with a list of chunks
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StripPolicy {
+    /// every ancillary chunk
+    All,
+    /// ancillary chunks with a private type
+    Private,
+    /// unregistered ancillary chunks that are not safe to copy after editing
+    UnsafeToCopy,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExtractMode {
+    /// only the chunk data
+    Data,
+    /// the chunk as stored in the file: length, type, data and crc
+    Raw,
+    /// a standalone chunk file that `inject` can read back in
+    Chunk,
+}
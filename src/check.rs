@@ -0,0 +1,609 @@
+//! pngcheck-style validation that reports every problem in a file instead
+//! of stopping at the first one.
+
+use std::fmt::Display;
+use std::io;
+
+use flate2::read::ZlibDecoder;
+
+use crate::checksum::Crc;
+use crate::chunk::ChunkRef;
+use crate::chunk_type::ChunkType;
+use crate::chunks::{ihdr::ColorType, ihdr::Ihdr, palette::Palette, palette::Transparency};
+use crate::png::Png;
+use crate::registry::{self, Placement, Registry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Offset of the offending chunk (or byte) from the start of the file.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning")?,
+            Severity::Error => write!(f, "error")?,
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset} ({offset:#x})")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+    /// The image header, if the file has a readable one.
+    pub ihdr: Option<Ihdr>,
+    /// Number of chunks up to and including IEND.
+    pub chunk_count: usize,
+}
+
+impl Report {
+    fn warn(&mut self, offset: impl Into<Option<usize>>, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            offset: offset.into(),
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, offset: impl Into<Option<usize>>, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            offset: offset.into(),
+            message: message.into(),
+        });
+    }
+
+    /// The most severe problem found, or `None` for a clean file.
+    pub fn severity(&self) -> Option<Severity> {
+        self.issues.iter().map(|issue| issue.severity).max()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+
+        let status = match self.severity() {
+            None => "OK",
+            Some(Severity::Warning) => "WARNINGS",
+            Some(Severity::Error) => "ERRORS",
+        };
+        write!(f, "{status}: {} chunks", self.chunk_count)?;
+        if let Some(ihdr) = &self.ihdr {
+            write!(
+                f,
+                ", {}x{}, {}-bit colour type {}, {}",
+                ihdr.width,
+                ihdr.height,
+                ihdr.bit_depth,
+                ihdr.color_type,
+                if ihdr.interlace_method == 1 {
+                    "interlaced"
+                } else {
+                    "non-interlaced"
+                }
+            )?;
+        }
+        write!(
+            f,
+            ", {} errors, {} warnings",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+/// Tracks what has been seen so far to validate the chunk order.
+#[derive(Default)]
+struct Walk<'a> {
+    seen: Vec<ChunkType>,
+    color_type: Option<ColorType>,
+    seen_plte: bool,
+    palette: Option<Palette>,
+    seen_idat: bool,
+    idat_ended: bool,
+    /// The data of every IDAT chunk, borrowed from the file.
+    idat: Vec<&'a [u8]>,
+}
+
+/// Reads a list of slices as one stream, so the image data does not have
+/// to be copied together before decompressing it.
+struct Slices<'a, 'b> {
+    slices: std::slice::Iter<'b, &'a [u8]>,
+    current: &'a [u8],
+}
+
+impl io::Read for Slices<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.slices.next() {
+                Some(next) => self.current = next,
+                None => return Ok(0),
+            }
+        }
+        self.current.read(buf)
+    }
+}
+
+/// Validates a whole PNG file.
+pub fn check(bytes: &[u8]) -> Report {
+    check_with(bytes, registry::standard())
+}
+
+/// Validates a whole PNG file, using `registry` to check the order and
+/// contents of chunks.
+pub fn check_with(bytes: &[u8], registry: &Registry) -> Report {
+    let mut report = Report::default();
+    let mut walk = Walk::default();
+
+    if bytes.len() < Png::STANDARD_HEADER.len() {
+        report.error(0, "file is too short to hold a PNG signature");
+        return report;
+    }
+    if bytes[..8] != Png::STANDARD_HEADER {
+        report.error(0, "invalid PNG signature");
+    }
+
+    let mut offset = Png::STANDARD_HEADER.len();
+    let mut seen_iend = false;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        if rest.len() < 12 {
+            report.error(
+                offset,
+                format!("truncated chunk, only {} bytes left", rest.len()),
+            );
+            break;
+        }
+
+        let length = u32::from_be_bytes(rest[0..4].try_into().unwrap());
+        let type_bytes: [u8; 4] = rest[4..8].try_into().unwrap();
+        let chunk_type = ChunkType::new_unchecked(type_bytes);
+        if length > i32::MAX as u32 {
+            report.error(offset, format!("{chunk_type} length {length} is too large"));
+            break;
+        }
+        if rest.len() < length as usize + 12 {
+            report.error(
+                offset,
+                format!(
+                    "truncated {chunk_type} chunk, expected {} bytes but only {} are left",
+                    length as usize + 12,
+                    rest.len()
+                ),
+            );
+            break;
+        }
+
+        let chunk = ChunkRef::read(rest).unwrap();
+        if !chunk.has_valid_crc() {
+            report.error(
+                offset,
+                format!(
+                    "{chunk_type} crc mismatch, stored {:#010x} but computed {:#010x}",
+                    chunk.crc(),
+                    Crc::checksum(&chunk_type, chunk.data())
+                ),
+            );
+        }
+
+        report.chunk_count += 1;
+        check_chunk(&mut report, &mut walk, registry, offset, &chunk);
+        offset += length as usize + 12;
+
+        if chunk_type == ChunkType::IEND {
+            seen_iend = true;
+            break;
+        }
+    }
+
+    if offset < bytes.len() && seen_iend {
+        report.warn(
+            offset,
+            format!("{} bytes of trailing data after IEND", bytes.len() - offset),
+        );
+    }
+    if !seen_iend {
+        report.error(None, "missing IEND chunk");
+    }
+    finish(&mut report, &walk);
+
+    report
+}
+
+fn check_chunk<'a>(
+    report: &mut Report,
+    walk: &mut Walk<'a>,
+    registry: &Registry,
+    offset: usize,
+    chunk: &ChunkRef<'a>,
+) {
+    let chunk_type = *chunk.chunk_type();
+    let type_bytes = chunk_type.bytes();
+    let info = registry.get(&chunk_type);
+
+    if !type_bytes.iter().all(u8::is_ascii_alphabetic) {
+        report.error(offset, format!("illegal chunk type {type_bytes:?}"));
+        return;
+    }
+    if !chunk_type.is_reserved_bit_valid() {
+        report.error(offset, format!("{chunk_type} has the reserved bit set"));
+    }
+
+    if walk.seen.is_empty() && chunk_type != ChunkType::IHDR {
+        report.error(offset, format!("first chunk is {chunk_type}, not IHDR"));
+    }
+    if info.is_some_and(|info| !info.multiple) && walk.seen.contains(&chunk_type) {
+        report.error(offset, format!("multiple {chunk_type} chunks"));
+    }
+
+    if chunk_type == ChunkType::IDAT {
+        if walk.idat_ended {
+            report.error(offset, "IDAT chunks are not consecutive");
+        }
+        walk.seen_idat = true;
+        walk.idat.push(chunk.data());
+    } else if walk.seen_idat {
+        walk.idat_ended = true;
+    }
+
+    match chunk_type {
+        ChunkType::IHDR => {
+            if let Ok(ihdr) = Ihdr::try_from(chunk) {
+                for problem in ihdr.problems() {
+                    report.error(offset, format!("IHDR {problem}"));
+                }
+                walk.color_type = Some(ihdr.color_type);
+                report.ihdr.get_or_insert(ihdr);
+            }
+        }
+        ChunkType::PLTE => {
+            if walk.seen_idat {
+                report.error(offset, "PLTE after IDAT");
+            }
+            if let Ok(palette) = Palette::try_from(chunk) {
+                if let Some(Err(error)) = report.ihdr.as_ref().map(|ihdr| palette.validate(ihdr)) {
+                    report.error(offset, error.to_string());
+                }
+                walk.palette = Some(palette);
+            }
+            walk.seen_plte = true;
+        }
+        ChunkType::IDAT | ChunkType::IEND => {}
+        _ if info.is_none() && chunk_type.is_critical() => {
+            report.error(offset, format!("unknown critical chunk {chunk_type}"));
+        }
+        _ => check_placement(
+            report,
+            walk,
+            offset,
+            chunk_type,
+            registry.placement(&chunk_type),
+        ),
+    }
+
+    if let (ChunkType::TRNS, Some(ihdr)) = (chunk_type, &report.ihdr) {
+        let trns = Transparency::parse(chunk, ihdr)
+            .and_then(|trns| trns.validate(ihdr, walk.palette.as_ref()));
+        if let Err(error) = trns {
+            report.error(offset, error.to_string());
+        }
+    }
+
+    // image data is checked as a whole once every chunk has been seen
+    let validate = info.filter(|_| chunk_type != ChunkType::IDAT);
+    if let Some(Err(error)) = validate.map(|info| info.validate(chunk)) {
+        report.error(offset, format!("malformed {chunk_type}: {error}"));
+    }
+
+    walk.seen.push(chunk_type);
+}
+
+fn check_placement(
+    report: &mut Report,
+    walk: &Walk,
+    offset: usize,
+    chunk_type: ChunkType,
+    placement: Placement,
+) {
+    match placement {
+        Placement::BeforePlte if walk.seen_plte || walk.seen_idat => {
+            report.error(
+                offset,
+                format!("{chunk_type} must come before PLTE and IDAT"),
+            );
+        }
+        Placement::AfterPlte => {
+            if walk.seen_idat {
+                report.error(offset, format!("{chunk_type} must come before IDAT"));
+            }
+            let needs_plte =
+                &chunk_type.bytes() == b"hIST" || walk.color_type == Some(ColorType::Indexed);
+            if needs_plte && !walk.seen_plte {
+                report.error(offset, format!("{chunk_type} must come after PLTE"));
+            }
+        }
+        Placement::BeforeIdat if walk.seen_idat => {
+            report.error(offset, format!("{chunk_type} must come before IDAT"));
+        }
+        _ => {}
+    }
+}
+
+fn finish(report: &mut Report, walk: &Walk) {
+    if !walk.seen_idat {
+        report.error(None, "no IDAT chunks");
+    } else if let Some(ihdr) = &report.ihdr {
+        let expected = ihdr.image_data_len();
+        let idat = Slices {
+            slices: walk.idat.iter(),
+            current: &[],
+        };
+        match io::copy(&mut ZlibDecoder::new(idat), &mut io::sink()) {
+            Err(error) => report.error(None, format!("corrupt IDAT stream: {error}")),
+            Ok(actual) if actual < expected => report.error(
+                None,
+                format!("truncated IDAT stream, expected {expected} bytes of image data but got {actual}"),
+            ),
+            Ok(actual) if actual > expected => report.warn(
+                None,
+                format!("IDAT stream holds {} bytes more than the image needs", actual - expected),
+            ),
+            Ok(_) => {}
+        }
+    }
+
+    if walk.color_type == Some(ColorType::Indexed) && !walk.seen_plte {
+        report.error(None, "indexed-colour image without PLTE");
+    }
+    if walk.seen.contains(&ChunkType::SRGB) && walk.seen.contains(&ChunkType::ICCP) {
+        report.warn(None, "both sRGB and iCCP are present");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunks::text::deflate;
+    use crate::registry::ChunkInfo;
+    use std::str::FromStr;
+
+    fn testing_ihdr() -> Ihdr {
+        Ihdr::new(2, 2, 8, ColorType::Grayscale)
+    }
+
+    fn testing_chunks() -> Vec<Chunk> {
+        vec![
+            testing_ihdr().to_chunk(),
+            Chunk::new(ChunkType::IDAT, deflate(&[0, 1, 2, 0, 3, 4])),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]
+    }
+
+    fn png_bytes(chunks: Vec<Chunk>) -> Vec<u8> {
+        Png::from_chunks(chunks).as_bytes()
+    }
+
+    fn messages(report: &Report) -> Vec<&str> {
+        report
+            .issues
+            .iter()
+            .map(|issue| issue.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        let report = check(&png_bytes(testing_chunks()));
+        assert_eq!(report.severity(), None, "{report}");
+        assert_eq!(report.chunk_count, 3);
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let mut bytes = png_bytes(testing_chunks());
+        bytes[0] = 0;
+        // corrupt the IHDR crc
+        bytes[8 + 12 + 13 - 1] ^= 0xff;
+        bytes.extend_from_slice(b"hidden");
+
+        let report = check(&bytes);
+        let messages = messages(&report);
+        assert_eq!(report.count(Severity::Error), 2, "{report}");
+        assert_eq!(report.count(Severity::Warning), 1, "{report}");
+        assert!(messages[0].contains("signature"));
+        assert!(messages[1].contains("crc mismatch"));
+        assert!(messages[2].contains("6 bytes of trailing data"));
+    }
+
+    #[test]
+    fn test_chunk_order() {
+        let mut chunks = testing_chunks();
+        chunks.insert(
+            2,
+            Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec()),
+        );
+        chunks.insert(1, Chunk::new(ChunkType::PLTE, vec![0, 0, 0]));
+        chunks.swap(0, 1);
+
+        let report = check(&png_bytes(chunks));
+        let messages = messages(&report);
+        assert!(
+            messages.contains(&"first chunk is PLTE, not IHDR"),
+            "{report}"
+        );
+        assert!(
+            messages.contains(&"gAMA must come before PLTE and IDAT"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn test_illegal_chunk_types() {
+        let mut chunks = testing_chunks();
+        chunks.insert(
+            1,
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), Vec::new()),
+        );
+        chunks.insert(
+            1,
+            Chunk::new(ChunkType::from_str("CrIt").unwrap(), Vec::new()),
+        );
+        chunks.insert(
+            1,
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), Vec::new()),
+        );
+        let mut bytes = png_bytes(chunks);
+        // turn the first ruSt into ru1t, the crc gets checked separately
+        bytes[8 + 25 + 6] = b'1';
+
+        let report = check(&bytes);
+        let messages = messages(&report);
+        assert!(
+            messages.iter().any(|m| m.starts_with("illegal chunk type")),
+            "{report}"
+        );
+        assert!(
+            messages.contains(&"unknown critical chunk CrIt"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn test_ihdr_inconsistency() {
+        let mut ihdr = testing_ihdr();
+        ihdr.bit_depth = 3;
+        let mut chunks = testing_chunks();
+        chunks[0] = ihdr.to_chunk();
+
+        let report = check(&png_bytes(chunks));
+        assert!(messages(&report)
+            .iter()
+            .any(|m| m.starts_with("IHDR bit depth 3")));
+    }
+
+    #[test]
+    fn test_palette_and_transparency() {
+        let mut chunks = testing_chunks();
+        chunks.insert(1, Chunk::new(ChunkType::TRNS, vec![1, 0]));
+        chunks.insert(1, Chunk::new(ChunkType::PLTE, vec![0, 0, 0]));
+
+        let report = check(&png_bytes(chunks));
+        let messages = messages(&report);
+        assert!(
+            messages.contains(&"colour type 0 (greyscale) does not allow a palette"),
+            "{report}"
+        );
+        assert!(
+            messages.contains(&"tRNS sample 256 does not fit a bit depth of 8"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn test_hostile_header() {
+        // found by fuzzing: sizes and bit depths no valid header has
+        let ihdr = Ihdr {
+            width: u32::MAX,
+            height: u32::MAX,
+            bit_depth: 252,
+            color_type: ColorType::Indexed,
+            interlace_method: 1,
+            ..testing_ihdr()
+        };
+        let mut chunks = testing_chunks();
+        chunks[0] = ihdr.to_chunk();
+        chunks.insert(1, Chunk::new(ChunkType::TRNS, vec![0, 128]));
+        chunks.insert(1, Chunk::new(ChunkType::PLTE, vec![0; 6]));
+
+        let report = check(&png_bytes(chunks));
+        assert_eq!(report.severity(), Some(Severity::Error), "{report}");
+    }
+
+    #[test]
+    fn test_truncated_idat_stream() {
+        let mut chunks = testing_chunks();
+        chunks[1] = Chunk::new(ChunkType::IDAT, deflate(&[0, 1, 2]));
+        let report = check(&png_bytes(chunks));
+        assert!(
+            messages(&report)
+                .iter()
+                .any(|m| m.starts_with("truncated IDAT stream")),
+            "{report}"
+        );
+
+        let mut chunks = testing_chunks();
+        let stream = deflate(&[0, 1, 2, 0, 3, 4]);
+        chunks[1] = Chunk::new(ChunkType::IDAT, stream[..stream.len() - 6].to_vec());
+        let report = check(&png_bytes(chunks));
+        assert_eq!(report.severity(), Some(Severity::Error), "{report}");
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let bytes = png_bytes(testing_chunks());
+        let report = check(&bytes[..bytes.len() - 3]);
+        let messages = messages(&report);
+        assert!(messages[0].starts_with("truncated chunk"), "{report}");
+        assert!(messages.contains(&"missing IEND chunk"));
+    }
+
+    #[test]
+    fn test_registered_chunks() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let mut chunks = testing_chunks();
+        chunks.insert(2, Chunk::new(chunk_type, vec![1]));
+        chunks.insert(2, Chunk::new(chunk_type, vec![0]));
+        let bytes = png_bytes(chunks);
+
+        let report = check(&bytes);
+        assert!(messages(&report).contains(&"unknown critical chunk RuSt"));
+
+        let mut registry = Registry::default();
+        registry
+            .register(
+                ChunkInfo::new(chunk_type, "Rust level")
+                    .single()
+                    .placement(Placement::BeforeIdat)
+                    .validator(|chunk| match chunk.data() {
+                        [0] => Ok(()),
+                        _ => Err(crate::Error::from("level must be 0")),
+                    }),
+            )
+            .unwrap();
+        let report = check_with(&bytes, &registry);
+        assert_eq!(
+            messages(&report),
+            [
+                "RuSt must come before IDAT",
+                "multiple RuSt chunks",
+                "RuSt must come before IDAT",
+                "malformed RuSt: level must be 0"
+            ],
+            "{report}"
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use std::fmt::Display;
 
-use crc::{Crc, CRC_32_ISO_HDLC};
-
+use crate::checksum::Crc;
 use crate::chunk_type::ChunkType;
 use crate::{Error, Result};
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
     length: u32,
     chunk_type: ChunkType,
@@ -12,18 +12,13 @@ pub struct Chunk {
     crc: u32,
 }
 
-const CRC_GEN: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
-
 impl Chunk {
-    fn calc_crc(chunk_type: &ChunkType, data: &Vec<u8>) -> u32 {
-        let crc_data: Vec<u8> = chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .cloned()
-            .collect();
+    /// Signature at the start of a standalone chunk file, as written by
+    /// `pngme extract --mode chunk` and read back by `pngme inject`.
+    pub const FILE_SIGNATURE: [u8; 8] = [137, 67, 72, 75, 13, 10, 26, 10];
 
-        CRC_GEN.checksum(&crc_data)
+    fn calc_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+        Crc::checksum(chunk_type, data)
     }
 
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
@@ -50,7 +45,7 @@ impl Chunk {
         bytes
     }
 
-    fn length(&self) -> u32 {
+    pub fn length(&self) -> u32 {
         self.length
     }
 
@@ -58,58 +53,181 @@ impl Chunk {
         &self.chunk_type
     }
 
-    fn data(&self) -> &[u8] {
+    pub fn data(&self) -> &[u8] {
         self.data.as_slice()
     }
 
-    fn crc(&self) -> u32 {
+    pub fn crc(&self) -> u32 {
         self.crc
     }
 
     pub fn data_as_string(&self) -> Result<String> {
         String::from_utf8(self.data.clone()).map_err(Error::from)
     }
+
+    pub fn has_valid_crc(&self) -> bool {
+        self.crc == Self::calc_crc(&self.chunk_type, &self.data)
+    }
+
+    /// Like `Chunk::try_from`, but keeps the stored crc even when it does
+    /// not match the chunk contents.
+    pub fn try_from_lenient(value: &[u8]) -> std::result::Result<Chunk, &'static str> {
+        let chunk = ChunkRef::read(value)?;
+        if chunk.size() != value.len() {
+            return Err("chunk length does not match its data");
+        }
+        Ok(chunk.to_owned())
+    }
+
+    /// Serialises the chunk as a standalone chunk file.
+    pub fn to_file_bytes(&self) -> Vec<u8> {
+        Self::FILE_SIGNATURE
+            .iter()
+            .cloned()
+            .chain(self.as_bytes())
+            .collect()
+    }
+
+    /// Reads a chunk back from a standalone chunk file. Plain raw chunks
+    /// without the signature are accepted as well.
+    pub fn from_file_bytes(bytes: &[u8]) -> Result<Chunk> {
+        let raw = bytes.strip_prefix(&Self::FILE_SIGNATURE).unwrap_or(bytes);
+        Chunk::try_from(raw).map_err(Error::from)
+    }
 }
 
 impl Display for Chunk {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // add more information in here
-        writeln!(f, "Chunk {{",)?;
-        writeln!(f, "  Length: {}", self.length())?;
-        writeln!(f, "  Type: {}", self.chunk_type())?;
-        writeln!(f, "  Data: {} bytes", self.data().len())?;
-        writeln!(f, "  Crc: {}", self.crc())?;
-        writeln!(f, "}}",)?;
-        Ok(())
+        ChunkRef::from(self).fmt(f)
     }
 }
 
 impl TryFrom<&[u8]> for Chunk {
     type Error = &'static str;
     fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
-        // add error handaling for invalid chunks
-
-        let (length_bytes, other) = value.split_at(4);
-        let length = u32::from_be_bytes(length_bytes.try_into().unwrap());
+        ChunkRef::try_from(value).map(|chunk| chunk.to_owned())
+    }
+}
 
-        let (chunk_type_bytes, other) = other.split_at(4);
-        let chunk_type_bytes: [u8; 4] = chunk_type_bytes.try_into().unwrap();
-        let chunk_type = ChunkType::try_from(chunk_type_bytes).unwrap();
+/// A chunk borrowed from an input buffer, for reading files without copying
+/// the chunk data. Owned chunks can be viewed as one with `ChunkRef::from`.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkRef<'a> {
+    chunk_type: ChunkType,
+    data: &'a [u8],
+    crc: u32,
+}
 
-        let (data, crc) = other.split_at(other.len() - 4);
-        let data = data.to_vec();
-        let crc = u32::from_be_bytes(crc.try_into().unwrap());
+impl<'a> ChunkRef<'a> {
+    /// Reads the chunk at the start of `bytes`, which may go on past the end
+    /// of the chunk. Like `Chunk::try_from_lenient`, neither the crc nor the
+    /// chunk type are checked.
+    pub fn read(bytes: &'a [u8]) -> std::result::Result<ChunkRef<'a>, &'static str> {
+        if bytes.len() < 12 {
+            return Err("chunk is shorter than 12 bytes");
+        }
+        let length = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        let bytes = bytes
+            .get(..length.saturating_add(12))
+            .ok_or("chunk runs past the end of the input")?;
+
+        Ok(ChunkRef {
+            chunk_type: ChunkType::new_unchecked(bytes[4..8].try_into().unwrap()),
+            data: &bytes[8..8 + length],
+            crc: u32::from_be_bytes(bytes[8 + length..].try_into().unwrap()),
+        })
+    }
 
-        if crc != Self::calc_crc(&chunk_type, &data) {
+    /// Checks what `Chunk::try_from` checks on top of `ChunkRef::read`.
+    pub(crate) fn verify(&self) -> std::result::Result<(), &'static str> {
+        if ChunkType::try_from(self.chunk_type.bytes()).is_err() {
+            return Err("chunk type contains non-letter bytes");
+        }
+        if !self.has_valid_crc() {
             return Err("Invalid crc");
         }
+        Ok(())
+    }
 
-        Ok(Chunk {
-            length,
-            data,
-            crc,
-            chunk_type,
-        })
+    pub fn length(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// Bytes the chunk takes up in the file, including the length, chunk
+    /// type and crc.
+    pub fn size(&self) -> usize {
+        self.data.len() + 12
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    pub fn has_valid_crc(&self) -> bool {
+        self.crc == Chunk::calc_crc(&self.chunk_type, self.data)
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        self.length()
+            .to_be_bytes()
+            .iter()
+            .chain(&self.chunk_type.bytes())
+            .chain(self.data)
+            .chain(&self.crc.to_be_bytes())
+            .cloned()
+            .collect()
+    }
+
+    /// Copies the chunk, keeping its stored crc.
+    pub fn to_owned(&self) -> Chunk {
+        Chunk {
+            length: self.length(),
+            chunk_type: self.chunk_type,
+            data: self.data.to_vec(),
+            crc: self.crc,
+        }
+    }
+}
+
+impl<'a> From<&'a Chunk> for ChunkRef<'a> {
+    fn from(chunk: &'a Chunk) -> ChunkRef<'a> {
+        ChunkRef {
+            chunk_type: chunk.chunk_type,
+            data: &chunk.data,
+            crc: chunk.crc,
+        }
+    }
+}
+
+impl Display for ChunkRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Chunk {{",)?;
+        writeln!(f, "  Length: {}", self.length())?;
+        writeln!(f, "  Type: {}", self.chunk_type())?;
+        writeln!(f, "  Data: {} bytes", self.data().len())?;
+        writeln!(f, "  Crc: {}", self.crc())?;
+        writeln!(f, "}}",)?;
+        Ok(())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for ChunkRef<'a> {
+    type Error = &'static str;
+    fn try_from(value: &'a [u8]) -> std::result::Result<Self, Self::Error> {
+        let chunk = ChunkRef::read(value)?;
+        if chunk.size() != value.len() {
+            return Err("chunk length does not match its data");
+        }
+        chunk.verify()?;
+        Ok(chunk)
     }
 }
 
@@ -117,6 +235,7 @@ impl TryFrom<&[u8]> for Chunk {
 mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
+    use proptest::prelude::*;
     use std::str::FromStr;
 
     fn raw_testing_chunk() -> Vec<u8> {
@@ -236,6 +355,81 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_chunk_ref() {
+        let mut bytes = raw_testing_chunk();
+        bytes.extend_from_slice(b"next chunk");
+
+        let chunk = ChunkRef::read(&bytes).unwrap();
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.size(), 54);
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.data(), b"This is where your secret message will be!");
+        assert_eq!(chunk.crc(), 2882656334);
+        assert!(chunk.has_valid_crc());
+        assert_eq!(chunk.as_bytes(), &bytes[..54]);
+        assert!(ChunkRef::try_from(bytes.as_slice()).is_err());
+        assert!(ChunkRef::try_from(&bytes[..54]).is_ok());
+
+        let owned = chunk.to_owned();
+        assert_eq!(owned.as_bytes(), chunk.as_bytes());
+        let view = ChunkRef::from(&owned);
+        assert_eq!(view.as_bytes(), chunk.as_bytes());
+        assert_eq!(view.data(), owned.data());
+
+        assert!(ChunkRef::read(&bytes[..40]).is_err());
+        bytes[20] ^= 1;
+        let chunk = ChunkRef::read(&bytes).unwrap();
+        assert!(!chunk.has_valid_crc());
+        assert!(ChunkRef::try_from(&bytes[..54]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_with_illegal_type() {
+        let mut bytes = Chunk::new(ChunkType::from_str("RuSt").unwrap(), Vec::new()).as_bytes();
+        bytes[6] = b'1';
+        assert!(Chunk::try_from(bytes.as_slice()).is_err());
+
+        let chunk = Chunk::try_from_lenient(bytes.as_slice()).unwrap();
+        assert_eq!(chunk.chunk_type().bytes(), *b"Ru1t");
+        assert!(!chunk.chunk_type().is_valid());
+    }
+
+    #[test]
+    fn test_lenient_chunk_from_bytes() {
+        let mut chunk_data = raw_testing_chunk();
+        let last = chunk_data.len() - 1;
+        chunk_data[last] ^= 1;
+
+        let chunk = Chunk::try_from_lenient(chunk_data.as_ref()).unwrap();
+        assert!(!chunk.has_valid_crc());
+        assert_eq!(chunk.crc(), 2882656335);
+        assert_eq!(chunk.as_bytes(), chunk_data);
+
+        assert!(testing_chunk().has_valid_crc());
+    }
+
+    #[test]
+    fn test_chunk_length_mismatch() {
+        let mut chunk_data = raw_testing_chunk();
+        chunk_data.truncate(chunk_data.len() - 5);
+        assert!(Chunk::try_from(chunk_data.as_ref()).is_err());
+        assert!(Chunk::try_from(&chunk_data[..8]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_file_round_trip() {
+        let chunk = testing_chunk();
+        let file = chunk.to_file_bytes();
+        assert_eq!(&file[..8], &Chunk::FILE_SIGNATURE);
+
+        let read_back = Chunk::from_file_bytes(&file).unwrap();
+        assert_eq!(read_back.as_bytes(), chunk.as_bytes());
+
+        let raw = Chunk::from_file_bytes(&chunk.as_bytes()).unwrap();
+        assert_eq!(raw.as_bytes(), chunk.as_bytes());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -256,4 +450,29 @@ mod tests {
 
         let _chunk_string = format!("{}", chunk);
     }
+
+    fn arb_chunk_type() -> impl Strategy<Value = ChunkType> {
+        "[a-zA-Z]{2}[A-Z][a-zA-Z]".prop_map(|name| ChunkType::from_str(&name).unwrap())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_chunk_round_trip(
+            chunk_type in arb_chunk_type(),
+            data in prop::collection::vec(any::<u8>(), 0..1024),
+        ) {
+            let chunk = Chunk::new(chunk_type, data);
+            let bytes = chunk.as_bytes();
+            prop_assert_eq!(Chunk::try_from(bytes.as_slice()).unwrap(), chunk.clone());
+            prop_assert_eq!(ChunkRef::try_from(bytes.as_slice()).unwrap().to_owned(), chunk.clone());
+            prop_assert_eq!(Chunk::from_file_bytes(&chunk.to_file_bytes()).unwrap(), chunk);
+        }
+
+        #[test]
+        fn prop_chunk_parser_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = Chunk::try_from(bytes.as_slice());
+            let _ = Chunk::try_from_lenient(bytes.as_slice());
+            let _ = ChunkRef::read(bytes.as_slice());
+        }
+    }
 }
@@ -3,39 +3,73 @@ use std::{
     str::{from_utf8, FromStr},
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChunkType([u8; 4]);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkTypeError {
+    /// The type is not exactly four bytes long.
+    Length(usize),
+    /// The name given to `ChunkType::generate` is not exactly two bytes long.
+    NameLength(usize),
+    /// A byte is not an ASCII letter.
+    NonLetter(u8),
+    /// The third letter is lowercase, which the specification reserves.
+    ReservedBit,
+}
+
+impl fmt::Display for ChunkTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkTypeError::Length(length) => {
+                write!(f, "chunk type is {length} bytes long instead of 4")
+            }
+            ChunkTypeError::NameLength(length) => {
+                write!(f, "chunk type name is {length} bytes long instead of 2")
+            }
+            ChunkTypeError::NonLetter(byte) => {
+                write!(f, "chunk type contains the non-letter byte {byte:#04x}")
+            }
+            ChunkTypeError::ReservedBit => write!(f, "chunk type has the reserved bit set"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkTypeError {}
+
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = &'static str;
+    type Error = ChunkTypeError;
 
     fn try_from(value: [u8; 4]) -> Result<Self, Self::Error> {
-        Ok(Self(value))
+        match value.iter().find(|byte| !byte.is_ascii_alphabetic()) {
+            Some(&byte) => Err(ChunkTypeError::NonLetter(byte)),
+            None => Ok(Self(value)),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct ParseChunkError;
-
 impl FromStr for ChunkType {
-    // TODO: REFACTOR (Custom Error)
-    type Err = &'static str; //ParseChunkError;
+    type Err = ChunkTypeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = s.as_bytes();
-        match bytes {
-            bytes if bytes.len() != 4 => Err("string length is not 4 bytes"),
-            bytes if !ChunkType::only_letters(bytes) => {
-                Err("string contains non ascii letter characters")
-            }
-            bytes => {
-                let array: [u8; 4] = bytes.try_into().unwrap();
-                Ok(Self(array))
-            }
-        }
+        let bytes: [u8; 4] = s
+            .as_bytes()
+            .try_into()
+            .map_err(|_| ChunkTypeError::Length(s.len()))?;
+        ChunkType::try_from(bytes)
     }
 }
 
+/// The four property bits encoded in the case of the letters of a chunk
+/// type, as a set bit (lowercase letter) would read them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Properties {
+    pub ancillary: bool,
+    pub private: bool,
+    pub reserved: bool,
+    pub safe_to_copy: bool,
+}
+
 impl fmt::Display for ChunkType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -46,36 +80,109 @@ impl fmt::Display for ChunkType {
     }
 }
 impl ChunkType {
-    fn only_letters(bytes: &[u8]) -> bool {
-        for c in bytes {
-            if !(65..=90).contains(c) && !(97..=122).contains(c) {
-                return false;
-            }
+    pub const IHDR: ChunkType = ChunkType(*b"IHDR");
+    pub const PLTE: ChunkType = ChunkType(*b"PLTE");
+    pub const IDAT: ChunkType = ChunkType(*b"IDAT");
+    pub const IEND: ChunkType = ChunkType(*b"IEND");
+    pub const TRNS: ChunkType = ChunkType(*b"tRNS");
+    pub const GAMA: ChunkType = ChunkType(*b"gAMA");
+    pub const CHRM: ChunkType = ChunkType(*b"cHRM");
+    pub const SBIT: ChunkType = ChunkType(*b"sBIT");
+    pub const SRGB: ChunkType = ChunkType(*b"sRGB");
+    pub const ICCP: ChunkType = ChunkType(*b"iCCP");
+    pub const CICP: ChunkType = ChunkType(*b"cICP");
+    pub const MDCV: ChunkType = ChunkType(*b"mDCv");
+    pub const CLLI: ChunkType = ChunkType(*b"cLLi");
+    pub const TEXT: ChunkType = ChunkType(*b"tEXt");
+    pub const ZTXT: ChunkType = ChunkType(*b"zTXt");
+    pub const ITXT: ChunkType = ChunkType(*b"iTXt");
+    pub const BKGD: ChunkType = ChunkType(*b"bKGD");
+    pub const HIST: ChunkType = ChunkType(*b"hIST");
+    pub const TIME: ChunkType = ChunkType(*b"tIME");
+    pub const PHYS: ChunkType = ChunkType(*b"pHYs");
+    pub const SPLT: ChunkType = ChunkType(*b"sPLT");
+    pub const EXIF: ChunkType = ChunkType(*b"eXIf");
+    pub const ACTL: ChunkType = ChunkType(*b"acTL");
+    pub const FCTL: ChunkType = ChunkType(*b"fcTL");
+    pub const FDAT: ChunkType = ChunkType(*b"fdAT");
+    pub const OFFS: ChunkType = ChunkType(*b"oFFs");
+    pub const PCAL: ChunkType = ChunkType(*b"pCAL");
+    pub const SCAL: ChunkType = ChunkType(*b"sCAL");
+
+    /// Builds a chunk type from any four bytes, for inspecting damaged or
+    /// non-conforming files. Prefer `ChunkType::try_from` everywhere else.
+    pub const fn new_unchecked(bytes: [u8; 4]) -> ChunkType {
+        ChunkType(bytes)
+    }
+
+    /// Builds a chunk type from a two-letter name and the property bits:
+    /// the name is used for both letter pairs, so "ru" with only
+    /// `ancillary` and `safe_to_copy` set gives "rURu".
+    pub fn generate(name: &str, properties: Properties) -> Result<ChunkType, ChunkTypeError> {
+        let name: [u8; 2] = name
+            .as_bytes()
+            .try_into()
+            .map_err(|_| ChunkTypeError::NameLength(name.len()))?;
+        let flags = [
+            properties.ancillary,
+            properties.private,
+            properties.reserved,
+            properties.safe_to_copy,
+        ];
+
+        let mut bytes = [name[0], name[1], name[0], name[1]];
+        for (byte, lowercase) in bytes.iter_mut().zip(flags) {
+            *byte = if lowercase {
+                byte.to_ascii_lowercase()
+            } else {
+                byte.to_ascii_uppercase()
+            };
         }
-        true
+        ChunkType::try_from(bytes)
     }
 
     pub fn bytes(&self) -> [u8; 4] {
         self.0
     }
 
-    fn is_valid(&self) -> bool {
-        ChunkType::only_letters(&self.0) && self.is_reserved_bit_valid()
+    /// Checks the chunk type against the specification: four ASCII letters
+    /// with the reserved bit clear.
+    pub fn validate(&self) -> Result<(), ChunkTypeError> {
+        if let Some(&byte) = self.0.iter().find(|byte| !byte.is_ascii_alphabetic()) {
+            return Err(ChunkTypeError::NonLetter(byte));
+        }
+        if !self.is_reserved_bit_valid() {
+            return Err(ChunkTypeError::ReservedBit);
+        }
+        Ok(())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
     }
 
-    fn is_critical(&self) -> bool {
+    pub fn properties(&self) -> Properties {
+        Properties {
+            ancillary: !self.is_critical(),
+            private: !self.is_public(),
+            reserved: !self.is_reserved_bit_valid(),
+            safe_to_copy: self.is_safe_to_copy(),
+        }
+    }
+
+    pub fn is_critical(&self) -> bool {
         self.0[0].is_ascii_uppercase()
     }
 
-    fn is_public(&self) -> bool {
+    pub fn is_public(&self) -> bool {
         self.0[1].is_ascii_uppercase()
     }
 
-    fn is_reserved_bit_valid(&self) -> bool {
+    pub fn is_reserved_bit_valid(&self) -> bool {
         self.0[2].is_ascii_uppercase()
     }
 
-    fn is_safe_to_copy(&self) -> bool {
+    pub fn is_safe_to_copy(&self) -> bool {
         self.0[3].is_ascii_lowercase()
     }
 }
@@ -83,6 +190,7 @@ impl ChunkType {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     use std::convert::TryFrom;
     use std::str::FromStr;
 
@@ -172,6 +280,50 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_chunk_type_errors() {
+        assert_eq!(
+            ChunkType::try_from(*b"Ru1t"),
+            Err(ChunkTypeError::NonLetter(b'1'))
+        );
+        assert_eq!(
+            ChunkType::from_str("Ru t"),
+            Err(ChunkTypeError::NonLetter(b' '))
+        );
+        assert_eq!(ChunkType::from_str("RuStY"), Err(ChunkTypeError::Length(5)));
+        assert_eq!(
+            ChunkType::from_str("Rust").unwrap().validate(),
+            Err(ChunkTypeError::ReservedBit)
+        );
+        assert_eq!(
+            ChunkType::new_unchecked(*b"Ru1t").validate(),
+            Err(ChunkTypeError::NonLetter(b'1'))
+        );
+        assert!(!ChunkType::new_unchecked(*b"Ru1t").is_valid());
+    }
+
+    #[test]
+    pub fn test_chunk_type_generate() {
+        let properties = Properties {
+            ancillary: true,
+            safe_to_copy: true,
+            ..Properties::default()
+        };
+        let chunk_type = ChunkType::generate("ru", properties).unwrap();
+        assert_eq!(chunk_type.to_string(), "rURu");
+        assert_eq!(chunk_type.properties(), properties);
+
+        let chunk_type = ChunkType::generate("Vp", Properties::default()).unwrap();
+        assert_eq!(chunk_type.to_string(), "VPVP");
+        assert!(chunk_type.is_critical() && !chunk_type.is_safe_to_copy());
+
+        assert_eq!(
+            ChunkType::generate("abc", properties),
+            Err(ChunkTypeError::NameLength(3))
+        );
+        assert!(ChunkType::generate("a1", properties).is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -185,4 +337,28 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    proptest! {
+        #[test]
+        fn prop_constructors_agree(bytes in any::<[u8; 4]>()) {
+            let from_bytes = ChunkType::try_from(bytes);
+            let from_str = std::str::from_utf8(&bytes)
+                .map_err(|_| ())
+                .and_then(|s| ChunkType::from_str(s).map_err(|_| ()));
+            prop_assert_eq!(from_bytes.is_ok(), bytes.iter().all(u8::is_ascii_alphabetic));
+            prop_assert_eq!(from_bytes.is_ok(), from_str.is_ok());
+            if let Ok(chunk_type) = from_bytes {
+                prop_assert_eq!(chunk_type.bytes(), bytes);
+                prop_assert_eq!(
+                    ChunkType::generate(
+                        std::str::from_utf8(&bytes[..2]).unwrap(),
+                        chunk_type.properties()
+                    )
+                    .unwrap()
+                    .properties(),
+                    chunk_type.properties()
+                );
+            }
+        }
+    }
 }
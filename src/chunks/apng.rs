@@ -0,0 +1,514 @@
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::chunks::ihdr::Ihdr;
+use crate::png::Png;
+use crate::{Error, Result};
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+/// The acTL chunk marking a file as animated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationControl {
+    pub num_frames: u32,
+    /// 0 means loop forever.
+    pub num_plays: u32,
+}
+
+impl AnimationControl {
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.num_frames.to_be_bytes(), self.num_plays.to_be_bytes()].concat();
+        Chunk::new(ChunkType::ACTL, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for AnimationControl {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() != 8 {
+            return Err(Error::from("acTL data must be 8 bytes long"));
+        }
+        Ok(AnimationControl {
+            num_frames: read_u32(data, 0),
+            num_plays: read_u32(data, 4),
+        })
+    }
+}
+
+from_owned_chunk!(AnimationControl);
+
+impl Display for AnimationControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Frames: {}", self.num_frames)?;
+        match self.num_plays {
+            0 => write!(f, "Plays: forever"),
+            plays => write!(f, "Plays: {plays}"),
+        }
+    }
+}
+
+/// How the frame area is treated before rendering the next frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisposeOp {
+    None = 0,
+    Background = 1,
+    Previous = 2,
+}
+
+/// How the frame is drawn onto the output buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Source = 0,
+    Over = 1,
+}
+
+/// The fcTL chunk describing the region, timing and compositing of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameControl {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp,
+}
+
+impl FrameControl {
+    /// Frame delay in seconds. A denominator of 0 stands for 100.
+    pub fn delay(&self) -> f64 {
+        let den = if self.delay_den == 0 {
+            100
+        } else {
+            self.delay_den
+        };
+        self.delay_num as f64 / den as f64
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = Vec::with_capacity(26);
+        for value in [
+            self.sequence_number,
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+        ] {
+            data.extend(value.to_be_bytes());
+        }
+        data.extend(self.delay_num.to_be_bytes());
+        data.extend(self.delay_den.to_be_bytes());
+        data.extend([self.dispose_op as u8, self.blend_op as u8]);
+
+        Chunk::new(ChunkType::FCTL, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for FrameControl {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() != 26 {
+            return Err(Error::from("fcTL data must be 26 bytes long"));
+        }
+
+        let dispose_op = match data[24] {
+            0 => DisposeOp::None,
+            1 => DisposeOp::Background,
+            2 => DisposeOp::Previous,
+            op => return Err(Error::from(format!("unknown dispose op {op}"))),
+        };
+        let blend_op = match data[25] {
+            0 => BlendOp::Source,
+            1 => BlendOp::Over,
+            op => return Err(Error::from(format!("unknown blend op {op}"))),
+        };
+
+        Ok(FrameControl {
+            sequence_number: read_u32(data, 0),
+            width: read_u32(data, 4),
+            height: read_u32(data, 8),
+            x_offset: read_u32(data, 12),
+            y_offset: read_u32(data, 16),
+            delay_num: read_u16(data, 20),
+            delay_den: read_u16(data, 22),
+            dispose_op,
+            blend_op,
+        })
+    }
+}
+
+from_owned_chunk!(FrameControl);
+
+impl Display for FrameControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Sequence number: {}", self.sequence_number)?;
+        writeln!(
+            f,
+            "Region: {}x{} at ({}, {})",
+            self.width, self.height, self.x_offset, self.y_offset
+        )?;
+        writeln!(
+            f,
+            "Delay: {}/{} ({:.3}s)",
+            self.delay_num,
+            self.delay_den,
+            self.delay()
+        )?;
+        writeln!(f, "Dispose op: {:?}", self.dispose_op)?;
+        write!(f, "Blend op: {:?}", self.blend_op)
+    }
+}
+
+/// The fdAT chunk carrying image data for frames after the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameData {
+    pub sequence_number: u32,
+    pub data: Vec<u8>,
+}
+
+impl FrameData {
+    pub fn to_chunk(&self) -> Chunk {
+        let data = self
+            .sequence_number
+            .to_be_bytes()
+            .iter()
+            .chain(&self.data)
+            .cloned()
+            .collect();
+        Chunk::new(ChunkType::FDAT, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for FrameData {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() < 4 {
+            return Err(Error::from("fdAT data must hold a sequence number"));
+        }
+        Ok(FrameData {
+            sequence_number: read_u32(data, 0),
+            data: data[4..].to_vec(),
+        })
+    }
+}
+
+from_owned_chunk!(FrameData);
+
+/// A single frame of an animated PNG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub control: FrameControl,
+    /// Whether the frame is the default image stored in IDAT.
+    pub is_default_image: bool,
+    /// The frame's zlib stream, joined from its IDAT or fdAT chunks.
+    pub data: Vec<u8>,
+}
+
+/// Splits the chunks of an animated PNG into frames, validating the
+/// shared fcTL/fdAT sequence numbers along the way.
+pub fn frames(chunks: &[Chunk]) -> Result<Vec<Frame>> {
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut next_sequence = 0;
+    let mut check_sequence = |sequence_number: u32| {
+        if sequence_number != next_sequence {
+            return Err(Error::from(format!(
+                "expected sequence number {next_sequence}, found {sequence_number}"
+            )));
+        }
+        next_sequence += 1;
+        Ok(())
+    };
+
+    let mut control = None;
+    for chunk in chunks {
+        match *chunk.chunk_type() {
+            ChunkType::ACTL => control = Some(AnimationControl::try_from(chunk)?),
+            ChunkType::FCTL => {
+                let frame_control = FrameControl::try_from(chunk)?;
+                check_sequence(frame_control.sequence_number)?;
+                frames.push(Frame {
+                    control: frame_control,
+                    is_default_image: false,
+                    data: Vec::new(),
+                });
+            }
+            ChunkType::IDAT => {
+                // IDAT only belongs to the animation if an fcTL precedes it
+                if let Some(frame) = frames.last_mut() {
+                    frame.is_default_image = true;
+                    frame.data.extend_from_slice(chunk.data());
+                }
+            }
+            ChunkType::FDAT => {
+                let frame_data = FrameData::try_from(chunk)?;
+                check_sequence(frame_data.sequence_number)?;
+                let frame = frames
+                    .last_mut()
+                    .ok_or("fdAT chunk before the first fcTL")?;
+                if frame.is_default_image {
+                    return Err(Error::from("fdAT chunk in the default image frame"));
+                }
+                frame.data.extend(frame_data.data);
+            }
+            _ => {}
+        }
+    }
+
+    let control = control.ok_or("not an animated png, no acTL chunk")?;
+    if control.num_frames as usize != frames.len() {
+        return Err(Error::from(format!(
+            "acTL announces {} frames but the file has {}",
+            control.num_frames,
+            frames.len()
+        )));
+    }
+    Ok(frames)
+}
+
+/// Chunks that describe the whole image and are copied into extracted
+/// frames and assembled animations.
+fn is_shared(chunk: &Chunk) -> bool {
+    !matches!(
+        *chunk.chunk_type(),
+        ChunkType::IHDR
+            | ChunkType::IDAT
+            | ChunkType::IEND
+            | ChunkType::ACTL
+            | ChunkType::FCTL
+            | ChunkType::FDAT
+    )
+}
+
+/// Builds a standalone PNG holding the region of a single frame.
+///
+/// The frame is not composited onto the previous ones, so for frames that
+/// only update part of the canvas the result is just that part.
+pub fn extract_frame(png: &Png, index: usize) -> Result<Png> {
+    let frames = png.frames()?;
+    let frame = frames
+        .get(index)
+        .ok_or_else(|| format!("frame {index} does not exist, there are {}", frames.len()))?;
+
+    let ihdr_chunk = png.chunk_by_type("IHDR").ok_or("missing IHDR chunk")?;
+    let mut ihdr = Ihdr::try_from(ihdr_chunk)?;
+    ihdr.width = frame.control.width;
+    ihdr.height = frame.control.height;
+
+    let mut chunks = vec![ihdr.to_chunk()];
+    let first_data = png
+        .chunks()
+        .iter()
+        .position(|chunk| matches!(*chunk.chunk_type(), ChunkType::IDAT | ChunkType::FDAT))
+        .unwrap_or(png.chunks().len());
+    chunks.extend(
+        png.chunks()[..first_data]
+            .iter()
+            .filter(|chunk| is_shared(chunk))
+            .map(|chunk| Chunk::new(*chunk.chunk_type(), chunk.data().to_vec())),
+    );
+    chunks.push(Chunk::new(ChunkType::IDAT, frame.data.clone()));
+    chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+
+    Ok(Png::from_chunks(chunks))
+}
+
+/// Combines still images into an animation. The first image sets the canvas
+/// size and provides the ancillary chunks; every frame is drawn at the top
+/// left corner and must fit onto the canvas.
+pub fn assemble(images: &[Png], delay: (u16, u16), num_plays: u32) -> Result<Png> {
+    let first = images.first().ok_or("at least one frame is needed")?;
+    let canvas = Ihdr::try_from(first.chunk_by_type("IHDR").ok_or("missing IHDR chunk")?)?;
+
+    let mut chunks = vec![canvas.to_chunk()];
+    chunks.push(
+        AnimationControl {
+            num_frames: images.len() as u32,
+            num_plays,
+        }
+        .to_chunk(),
+    );
+    chunks.extend(
+        first
+            .chunks()
+            .iter()
+            .take_while(|chunk| chunk.chunk_type() != &ChunkType::IDAT)
+            .filter(|chunk| is_shared(chunk))
+            .map(|chunk| Chunk::new(*chunk.chunk_type(), chunk.data().to_vec())),
+    );
+
+    let mut sequence_number = 0;
+    for (index, image) in images.iter().enumerate() {
+        let ihdr = Ihdr::try_from(image.chunk_by_type("IHDR").ok_or("missing IHDR chunk")?)?;
+        if (ihdr.bit_depth, ihdr.color_type, ihdr.interlace_method)
+            != (canvas.bit_depth, canvas.color_type, canvas.interlace_method)
+        {
+            return Err(Error::from(format!(
+                "frame {index} has a different pixel format than frame 0"
+            )));
+        }
+        if ihdr.width > canvas.width || ihdr.height > canvas.height {
+            return Err(Error::from(format!(
+                "frame {index} is larger than the {}x{} canvas",
+                canvas.width, canvas.height
+            )));
+        }
+        if image.chunk_by_type("PLTE").map(Chunk::data)
+            != first.chunk_by_type("PLTE").map(Chunk::data)
+        {
+            return Err(Error::from(format!(
+                "frame {index} has a different palette than frame 0"
+            )));
+        }
+
+        let control = FrameControl {
+            sequence_number,
+            width: ihdr.width,
+            height: ihdr.height,
+            x_offset: 0,
+            y_offset: 0,
+            delay_num: delay.0,
+            delay_den: delay.1,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        };
+        chunks.push(control.to_chunk());
+        sequence_number += 1;
+
+        let data: Vec<u8> = image
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == &ChunkType::IDAT)
+            .flat_map(|chunk| chunk.data().iter().cloned())
+            .collect();
+        if index == 0 {
+            chunks.push(Chunk::new(ChunkType::IDAT, data));
+        } else {
+            chunks.push(
+                FrameData {
+                    sequence_number,
+                    data,
+                }
+                .to_chunk(),
+            );
+            sequence_number += 1;
+        }
+    }
+    chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+
+    Ok(Png::from_chunks(chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::ihdr::ColorType;
+
+    fn still(width: u32, height: u32, data: &[u8]) -> Png {
+        let ihdr = Ihdr::new(width, height, 8, ColorType::Grayscale);
+        Png::from_chunks(vec![
+            ihdr.to_chunk(),
+            Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec()),
+            Chunk::new(ChunkType::IDAT, data.to_vec()),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ])
+    }
+
+    fn testing_animation() -> Png {
+        let frames = [
+            still(4, 4, b"one"),
+            still(2, 2, b"two"),
+            still(4, 4, b"three"),
+        ];
+        assemble(&frames, (1, 10), 0).unwrap()
+    }
+
+    #[test]
+    fn test_frame_control_round_trip() {
+        let control = FrameControl {
+            sequence_number: 3,
+            width: 10,
+            height: 20,
+            x_offset: 1,
+            y_offset: 2,
+            delay_num: 1,
+            delay_den: 0,
+            dispose_op: DisposeOp::Previous,
+            blend_op: BlendOp::Over,
+        };
+        let chunk = control.to_chunk();
+        assert_eq!(chunk.length(), 26);
+        assert_eq!(FrameControl::try_from(&chunk).unwrap(), control);
+        assert_eq!(control.delay(), 0.01);
+    }
+
+    #[test]
+    fn test_assemble_and_frames() {
+        let png = testing_animation();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "acTL", "gAMA", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]
+        );
+
+        let frames = png.frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].is_default_image);
+        assert_eq!(frames[1].data, b"two");
+        assert_eq!((frames[1].control.width, frames[1].control.height), (2, 2));
+        assert_eq!(frames[2].control.sequence_number, 3);
+    }
+
+    #[test]
+    fn test_sequence_gap() {
+        let mut png = testing_animation();
+        png.remove_chunk("fcTL").unwrap();
+        assert!(png.frames().is_err());
+    }
+
+    #[test]
+    fn test_not_animated() {
+        assert!(still(1, 1, b"").frames().is_err());
+    }
+
+    #[test]
+    fn test_extract_frame() {
+        let png = testing_animation();
+        let frame = extract_frame(&png, 1).unwrap();
+        let types: Vec<String> = frame
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "gAMA", "IDAT", "IEND"]);
+        assert_eq!(frame.chunk_by_type("IDAT").unwrap().data(), b"two");
+
+        let ihdr = Ihdr::try_from(frame.chunk_by_type("IHDR").unwrap()).unwrap();
+        assert_eq!((ihdr.width, ihdr.height), (2, 2));
+        assert!(extract_frame(&png, 3).is_err());
+    }
+
+    #[test]
+    fn test_assemble_rejects_larger_frames() {
+        let frames = [still(2, 2, b"one"), still(4, 4, b"two")];
+        assert!(assemble(&frames, (1, 10), 0).is_err());
+    }
+}
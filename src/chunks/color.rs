@@ -0,0 +1,635 @@
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::chunks::text::{deflate, encode_keyword, inflate, latin1, split_null};
+use crate::png::Png;
+use crate::{Error, Result};
+
+/// Every chunk type that describes the colour space of an image.
+pub const COLOR_CHUNKS: [ChunkType; 7] = [
+    ChunkType::GAMA,
+    ChunkType::CHRM,
+    ChunkType::SRGB,
+    ChunkType::ICCP,
+    ChunkType::CICP,
+    ChunkType::MDCV,
+    ChunkType::CLLI,
+];
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes(data[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+fn read_u16(data: &[u8], index: usize) -> u16 {
+    u16::from_be_bytes([data[index * 2], data[index * 2 + 1]])
+}
+
+/// Image gamma from a gAMA chunk, stored as gamma times 100000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gamma(pub u32);
+
+impl Gamma {
+    pub fn value(&self) -> f64 {
+        self.0 as f64 / 100_000.0
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::GAMA, self.0.to_be_bytes().to_vec())
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Gamma {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        if chunk.data().len() != 4 {
+            return Err(Error::from("gAMA data must be 4 bytes long"));
+        }
+        Ok(Gamma(read_u32(chunk.data(), 0)))
+    }
+}
+
+from_owned_chunk!(Gamma);
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Gamma: {:.5}", self.value())
+    }
+}
+
+/// Primary chromaticities and white point from a cHRM chunk, each stored as
+/// the CIE value times 100000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chromaticities {
+    pub white: (u32, u32),
+    pub red: (u32, u32),
+    pub green: (u32, u32),
+    pub blue: (u32, u32),
+}
+
+impl Chromaticities {
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.white, self.red, self.green, self.blue]
+            .iter()
+            .flat_map(|(x, y)| [x.to_be_bytes(), y.to_be_bytes()])
+            .flatten()
+            .collect();
+        Chunk::new(ChunkType::CHRM, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Chromaticities {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() != 32 {
+            return Err(Error::from("cHRM data must be 32 bytes long"));
+        }
+        let point = |index| (read_u32(data, index), read_u32(data, index + 1));
+
+        Ok(Chromaticities {
+            white: point(0),
+            red: point(2),
+            green: point(4),
+            blue: point(6),
+        })
+    }
+}
+
+from_owned_chunk!(Chromaticities);
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = [
+            ("White point", self.white),
+            ("Red", self.red),
+            ("Green", self.green),
+            ("Blue", self.blue),
+        ];
+        for (index, (name, (x, y))) in points.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{name}: x = {:.5}, y = {:.5}",
+                *x as f64 / 100_000.0,
+                *y as f64 / 100_000.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Rendering intent from an sRGB chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingIntent {
+    Perceptual = 0,
+    RelativeColorimetric = 1,
+    Saturation = 2,
+    AbsoluteColorimetric = 3,
+}
+
+impl RenderingIntent {
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SRGB, vec![*self as u8])
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for RenderingIntent {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        match chunk.data() {
+            [0] => Ok(RenderingIntent::Perceptual),
+            [1] => Ok(RenderingIntent::RelativeColorimetric),
+            [2] => Ok(RenderingIntent::Saturation),
+            [3] => Ok(RenderingIntent::AbsoluteColorimetric),
+            [intent] => Err(Error::from(format!("unknown rendering intent {intent}"))),
+            _ => Err(Error::from("sRGB data must be 1 byte long")),
+        }
+    }
+}
+
+from_owned_chunk!(RenderingIntent);
+
+impl Display for RenderingIntent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RenderingIntent::Perceptual => "perceptual",
+            RenderingIntent::RelativeColorimetric => "relative colorimetric",
+            RenderingIntent::Saturation => "saturation",
+            RenderingIntent::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "Rendering intent: {} ({name})", *self as u8)
+    }
+}
+
+/// An embedded ICC profile from an iCCP chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    pub name: String,
+    /// The uncompressed profile.
+    pub profile: Vec<u8>,
+}
+
+impl IccProfile {
+    /// Checks the name against the keyword rules and the profile for an
+    /// ICC header.
+    pub fn new(name: &str, profile: Vec<u8>) -> Result<IccProfile> {
+        encode_keyword(name).map_err(|error| format!("bad profile name: {error}"))?;
+        if profile.len() < 128 || &profile[36..40] != b"acsp" {
+            return Err(Error::from("not an ICC profile"));
+        }
+
+        Ok(IccProfile {
+            name: name.to_string(),
+            profile,
+        })
+    }
+
+    /// The data colour space signature from the profile header, such as
+    /// `RGB` or `GRAY`.
+    pub fn color_space(&self) -> String {
+        latin1(&self.profile[16..20]).trim_end().to_string()
+    }
+
+    /// Fails if the name is not a valid keyword.
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = encode_keyword(&self.name)?;
+        data.extend([0, 0]);
+        data.extend(deflate(&self.profile));
+        Ok(Chunk::new(ChunkType::ICCP, data))
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for IccProfile {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let (name, rest) = split_null(chunk.data())?;
+        let (&method, compressed) = rest.split_first().ok_or("missing compression method")?;
+        if method != 0 {
+            return Err(Error::from(format!("unknown compression method {method}")));
+        }
+
+        IccProfile::new(&latin1(name), inflate(compressed)?)
+    }
+}
+
+from_owned_chunk!(IccProfile);
+
+impl Display for IccProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Profile name: {}", self.name)?;
+        writeln!(f, "Profile size: {} bytes", self.profile.len())?;
+        write!(f, "Profile colour space: {}", self.color_space())
+    }
+}
+
+/// Coding-independent code points from a cICP chunk, as defined by
+/// ITU-T H.273.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cicp {
+    pub color_primaries: u8,
+    pub transfer_function: u8,
+    /// Always 0 in PNG, which only stores RGB.
+    pub matrix_coefficients: u8,
+    pub full_range: bool,
+}
+
+impl Cicp {
+    fn primaries_name(&self) -> &'static str {
+        match self.color_primaries {
+            1 => "BT.709",
+            4 => "BT.470 M",
+            5 => "BT.470 BG",
+            6 => "BT.601",
+            7 => "SMPTE 240M",
+            9 => "BT.2020",
+            10 => "CIE XYZ",
+            11 => "DCI-P3",
+            12 => "Display P3",
+            22 => "EBU 3213",
+            _ => "unknown",
+        }
+    }
+
+    fn transfer_name(&self) -> &'static str {
+        match self.transfer_function {
+            1 | 6 | 14 | 15 => "BT.709",
+            4 => "gamma 2.2",
+            5 => "gamma 2.8",
+            8 => "linear",
+            13 => "sRGB",
+            16 => "PQ",
+            18 => "HLG",
+            _ => "unknown",
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = vec![
+            self.color_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            self.full_range as u8,
+        ];
+        Chunk::new(ChunkType::CICP, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Cicp {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let &[color_primaries, transfer_function, matrix_coefficients, full_range] = chunk.data()
+        else {
+            return Err(Error::from("cICP data must be 4 bytes long"));
+        };
+        if matrix_coefficients != 0 {
+            return Err(Error::from("cICP matrix coefficients must be 0"));
+        }
+        if full_range > 1 {
+            return Err(Error::from(format!("invalid cICP range flag {full_range}")));
+        }
+
+        Ok(Cicp {
+            color_primaries,
+            transfer_function,
+            matrix_coefficients,
+            full_range: full_range == 1,
+        })
+    }
+}
+
+from_owned_chunk!(Cicp);
+
+impl Display for Cicp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Colour primaries: {} ({})",
+            self.color_primaries,
+            self.primaries_name()
+        )?;
+        writeln!(
+            f,
+            "Transfer function: {} ({})",
+            self.transfer_function,
+            self.transfer_name()
+        )?;
+        write!(
+            f,
+            "Range: {}",
+            if self.full_range { "full" } else { "narrow" }
+        )
+    }
+}
+
+/// Mastering display colour volume from an mDCv chunk. Chromaticities are
+/// stored in units of 0.00002 and luminances in units of 0.0001 cd/m².
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MasteringDisplay {
+    pub red: (u16, u16),
+    pub green: (u16, u16),
+    pub blue: (u16, u16),
+    pub white: (u16, u16),
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+impl MasteringDisplay {
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data: Vec<u8> = [self.red, self.green, self.blue, self.white]
+            .iter()
+            .flat_map(|(x, y)| [x.to_be_bytes(), y.to_be_bytes()])
+            .flatten()
+            .collect();
+        data.extend(self.max_luminance.to_be_bytes());
+        data.extend(self.min_luminance.to_be_bytes());
+        Chunk::new(ChunkType::MDCV, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for MasteringDisplay {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() != 24 {
+            return Err(Error::from("mDCv data must be 24 bytes long"));
+        }
+        let point = |index| (read_u16(data, index), read_u16(data, index + 1));
+
+        Ok(MasteringDisplay {
+            red: point(0),
+            green: point(2),
+            blue: point(4),
+            white: point(6),
+            max_luminance: read_u32(data, 4),
+            min_luminance: read_u32(data, 5),
+        })
+    }
+}
+
+from_owned_chunk!(MasteringDisplay);
+
+impl Display for MasteringDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = [
+            ("Red", self.red),
+            ("Green", self.green),
+            ("Blue", self.blue),
+            ("White point", self.white),
+        ];
+        for (name, (x, y)) in points {
+            writeln!(
+                f,
+                "{name}: x = {:.5}, y = {:.5}",
+                x as f64 * 0.00002,
+                y as f64 * 0.00002
+            )?;
+        }
+        write!(
+            f,
+            "Luminance: {:.4} to {:.4} cd/m²",
+            self.min_luminance as f64 / 10_000.0,
+            self.max_luminance as f64 / 10_000.0
+        )
+    }
+}
+
+/// Content light level information from a cLLi chunk, in units of
+/// 0.0001 cd/m².
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLightLevel {
+    pub max_content: u32,
+    pub max_frame_average: u32,
+}
+
+impl ContentLightLevel {
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.max_content, self.max_frame_average]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        Chunk::new(ChunkType::CLLI, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for ContentLightLevel {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.len() != 8 {
+            return Err(Error::from("cLLi data must be 8 bytes long"));
+        }
+
+        Ok(ContentLightLevel {
+            max_content: read_u32(data, 0),
+            max_frame_average: read_u32(data, 1),
+        })
+    }
+}
+
+from_owned_chunk!(ContentLightLevel);
+
+impl Display for ContentLightLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Maximum content light level: {:.4} cd/m²",
+            self.max_content as f64 / 10_000.0
+        )?;
+        write!(
+            f,
+            "Maximum frame-average light level: {:.4} cd/m²",
+            self.max_frame_average as f64 / 10_000.0
+        )
+    }
+}
+
+/// The colour space a decoder should use. When several chunks are present
+/// cICP wins over iCCP, which wins over sRGB, which wins over gAMA and cHRM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorSpace {
+    Cicp(Cicp),
+    Icc(IccProfile),
+    Srgb(RenderingIntent),
+    Calibrated {
+        gamma: Option<Gamma>,
+        chromaticities: Option<Chromaticities>,
+    },
+    Unspecified,
+}
+
+impl ColorSpace {
+    pub fn of(png: &Png) -> Result<ColorSpace> {
+        let find = |chunk_type: &str| png.chunk_by_type(chunk_type);
+
+        if let Some(chunk) = find("cICP") {
+            return Ok(ColorSpace::Cicp(Cicp::try_from(chunk)?));
+        }
+        if let Some(chunk) = find("iCCP") {
+            return Ok(ColorSpace::Icc(IccProfile::try_from(chunk)?));
+        }
+        if let Some(chunk) = find("sRGB") {
+            return Ok(ColorSpace::Srgb(RenderingIntent::try_from(chunk)?));
+        }
+
+        let gamma = find("gAMA").map(Gamma::try_from).transpose()?;
+        let chromaticities = find("cHRM").map(Chromaticities::try_from).transpose()?;
+        if gamma.is_none() && chromaticities.is_none() {
+            return Ok(ColorSpace::Unspecified);
+        }
+        Ok(ColorSpace::Calibrated {
+            gamma,
+            chromaticities,
+        })
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorSpace::Cicp(cicp) => write!(f, "Colour space: cICP\n{cicp}"),
+            ColorSpace::Icc(profile) => write!(f, "Colour space: ICC profile\n{profile}"),
+            ColorSpace::Srgb(intent) => write!(f, "Colour space: sRGB\n{intent}"),
+            ColorSpace::Calibrated {
+                gamma,
+                chromaticities,
+            } => {
+                write!(f, "Colour space: calibrated")?;
+                if let Some(gamma) = gamma {
+                    write!(f, "\n{gamma}")?;
+                }
+                if let Some(chromaticities) = chromaticities {
+                    write!(f, "\n{chromaticities}")?;
+                }
+                Ok(())
+            }
+            ColorSpace::Unspecified => write!(f, "Colour space: unspecified"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_profile() -> Vec<u8> {
+        let mut profile = vec![0; 128];
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile[36..40].copy_from_slice(b"acsp");
+        profile
+    }
+
+    #[test]
+    fn test_gamma() {
+        let chunk = Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec());
+        let gamma = Gamma::try_from(&chunk).unwrap();
+        assert_eq!(gamma, Gamma(45455));
+        assert_eq!(gamma.to_string(), "Gamma: 0.45455");
+    }
+
+    #[test]
+    fn test_chromaticities() {
+        let data: Vec<u8> = [31270u32, 32900, 64000, 33000, 30000, 60000, 15000, 6000]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        let chunk = Chunk::new(ChunkType::CHRM, data);
+        let chrm = Chromaticities::try_from(&chunk).unwrap();
+        assert_eq!(chrm.white, (31270, 32900));
+        assert_eq!(chrm.blue, (15000, 6000));
+        assert_eq!(chrm.to_chunk().data(), chunk.data());
+    }
+
+    #[test]
+    fn test_rendering_intent() {
+        let chunk = Chunk::new(ChunkType::SRGB, vec![0]);
+        let intent = RenderingIntent::try_from(&chunk).unwrap();
+        assert_eq!(intent, RenderingIntent::Perceptual);
+
+        let chunk = Chunk::new(ChunkType::SRGB, vec![4]);
+        assert!(RenderingIntent::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_icc_profile_round_trip() {
+        let profile = IccProfile::new("Display", testing_profile()).unwrap();
+        let chunk = profile.to_chunk().unwrap();
+        assert!(chunk.data().starts_with(b"Display\0\0"));
+        assert_eq!(IccProfile::try_from(&chunk).unwrap(), profile);
+        assert_eq!(profile.color_space(), "RGB");
+    }
+
+    #[test]
+    fn test_icc_profile_invalid() {
+        assert!(IccProfile::new("", testing_profile()).is_err());
+        assert!(IccProfile::new(&"x".repeat(80), testing_profile()).is_err());
+        assert!(IccProfile::new("Display", vec![0; 128]).is_err());
+    }
+
+    #[test]
+    fn test_cicp() {
+        let chunk = Chunk::new(ChunkType::CICP, vec![9, 16, 0, 1]);
+        let cicp = Cicp::try_from(&chunk).unwrap();
+        assert_eq!(cicp.primaries_name(), "BT.2020");
+        assert_eq!(cicp.transfer_name(), "PQ");
+        assert!(cicp.full_range);
+        assert_eq!(cicp.to_chunk().data(), chunk.data());
+
+        let chunk = Chunk::new(ChunkType::CICP, vec![1, 13, 1, 1]);
+        assert!(Cicp::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_hdr_metadata_round_trip() {
+        let mdcv = MasteringDisplay {
+            red: (35400, 14600),
+            green: (8500, 39850),
+            blue: (6550, 2300),
+            white: (15635, 16450),
+            max_luminance: 10_000_000,
+            min_luminance: 1,
+        };
+        assert_eq!(MasteringDisplay::try_from(&mdcv.to_chunk()).unwrap(), mdcv);
+
+        let clli = ContentLightLevel {
+            max_content: 4_000_000,
+            max_frame_average: 1_000_000,
+        };
+        assert_eq!(ContentLightLevel::try_from(&clli.to_chunk()).unwrap(), clli);
+    }
+
+    #[test]
+    fn test_color_space_precedence() {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]);
+        assert_eq!(ColorSpace::of(&png).unwrap(), ColorSpace::Unspecified);
+
+        png.set_chunk(Gamma(45455).to_chunk());
+        assert_eq!(
+            ColorSpace::of(&png).unwrap(),
+            ColorSpace::Calibrated {
+                gamma: Some(Gamma(45455)),
+                chromaticities: None
+            }
+        );
+
+        png.set_chunk(RenderingIntent::Perceptual.to_chunk());
+        let profile = IccProfile::new("Display", testing_profile()).unwrap();
+        png.set_chunk(profile.to_chunk().unwrap());
+        assert!(matches!(ColorSpace::of(&png).unwrap(), ColorSpace::Icc(_)));
+
+        png.set_chunk(Chunk::new(ChunkType::CICP, vec![1, 13, 0, 1]));
+        assert!(matches!(ColorSpace::of(&png).unwrap(), ColorSpace::Cicp(_)));
+    }
+}
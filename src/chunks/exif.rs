@@ -0,0 +1,991 @@
+//! EXIF metadata from an eXIf chunk, which holds a TIFF structure of image
+//! file directories (IFDs).
+//!
+//! Parsing follows the IFD0, Exif, GPS, Interoperability and IFD1
+//! directories. Writing lays them out again from scratch, so offsets inside
+//! opaque values such as maker notes are not preserved.
+
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+const EXIF_POINTER: u16 = 0x8769;
+const GPS_POINTER: u16 = 0x8825;
+const INTEROP_POINTER: u16 = 0xa005;
+const THUMBNAIL_OFFSET: u16 = 0x0201;
+const THUMBNAIL_LENGTH: u16 = 0x0202;
+/// More fields than any camera writes, to bound the work on hostile data.
+const MAX_FIELDS: usize = 1 << 16;
+
+const BYTE: u16 = 1;
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+const SBYTE: u16 = 6;
+const UNDEFINED: u16 = 7;
+const SSHORT: u16 = 8;
+const SLONG: u16 = 9;
+const SRATIONAL: u16 = 10;
+const FLOAT: u16 = 11;
+const DOUBLE: u16 = 12;
+/// Used by some writers for sub-IFD pointers, read as LONG.
+const IFD: u16 = 13;
+
+/// Well-known tags with the type used when a tag is newly added.
+const TAGS: &[(Ifd, u16, &str, u16)] = &[
+    (Ifd::Primary, 0x010e, "ImageDescription", ASCII),
+    (Ifd::Primary, 0x010f, "Make", ASCII),
+    (Ifd::Primary, 0x0110, "Model", ASCII),
+    (Ifd::Primary, 0x0112, "Orientation", SHORT),
+    (Ifd::Primary, 0x011a, "XResolution", RATIONAL),
+    (Ifd::Primary, 0x011b, "YResolution", RATIONAL),
+    (Ifd::Primary, 0x0128, "ResolutionUnit", SHORT),
+    (Ifd::Primary, 0x0131, "Software", ASCII),
+    (Ifd::Primary, 0x0132, "DateTime", ASCII),
+    (Ifd::Primary, 0x013b, "Artist", ASCII),
+    (Ifd::Primary, 0x8298, "Copyright", ASCII),
+    (Ifd::Exif, 0x829a, "ExposureTime", RATIONAL),
+    (Ifd::Exif, 0x829d, "FNumber", RATIONAL),
+    (Ifd::Exif, 0x8822, "ExposureProgram", SHORT),
+    (Ifd::Exif, 0x8827, "ISOSpeedRatings", SHORT),
+    (Ifd::Exif, 0x9000, "ExifVersion", UNDEFINED),
+    (Ifd::Exif, 0x9003, "DateTimeOriginal", ASCII),
+    (Ifd::Exif, 0x9004, "DateTimeDigitized", ASCII),
+    (Ifd::Exif, 0x9010, "OffsetTime", ASCII),
+    (Ifd::Exif, 0x9011, "OffsetTimeOriginal", ASCII),
+    (Ifd::Exif, 0x9201, "ShutterSpeedValue", SRATIONAL),
+    (Ifd::Exif, 0x9202, "ApertureValue", RATIONAL),
+    (Ifd::Exif, 0x9204, "ExposureBiasValue", SRATIONAL),
+    (Ifd::Exif, 0x9207, "MeteringMode", SHORT),
+    (Ifd::Exif, 0x9209, "Flash", SHORT),
+    (Ifd::Exif, 0x920a, "FocalLength", RATIONAL),
+    (Ifd::Exif, 0x927c, "MakerNote", UNDEFINED),
+    (Ifd::Exif, 0x9286, "UserComment", UNDEFINED),
+    (Ifd::Exif, 0xa001, "ColorSpace", SHORT),
+    (Ifd::Exif, 0xa002, "PixelXDimension", LONG),
+    (Ifd::Exif, 0xa003, "PixelYDimension", LONG),
+    (Ifd::Exif, 0xa405, "FocalLengthIn35mmFilm", SHORT),
+    (Ifd::Exif, 0xa420, "ImageUniqueID", ASCII),
+    (Ifd::Exif, 0xa430, "CameraOwnerName", ASCII),
+    (Ifd::Exif, 0xa431, "BodySerialNumber", ASCII),
+    (Ifd::Exif, 0xa433, "LensMake", ASCII),
+    (Ifd::Exif, 0xa434, "LensModel", ASCII),
+    (Ifd::Gps, 0x0000, "GPSVersionID", BYTE),
+    (Ifd::Gps, 0x0001, "GPSLatitudeRef", ASCII),
+    (Ifd::Gps, 0x0002, "GPSLatitude", RATIONAL),
+    (Ifd::Gps, 0x0003, "GPSLongitudeRef", ASCII),
+    (Ifd::Gps, 0x0004, "GPSLongitude", RATIONAL),
+    (Ifd::Gps, 0x0005, "GPSAltitudeRef", BYTE),
+    (Ifd::Gps, 0x0006, "GPSAltitude", RATIONAL),
+    (Ifd::Gps, 0x0007, "GPSTimeStamp", RATIONAL),
+    (Ifd::Gps, 0x001d, "GPSDateStamp", ASCII),
+    (Ifd::Interop, 0x0001, "InteroperabilityIndex", ASCII),
+];
+
+/// Looks up the name of a well-known tag.
+pub fn tag_name(ifd: Ifd, tag: u16) -> Option<&'static str> {
+    TAGS.iter()
+        .find(|(tag_ifd, number, _, _)| *tag_ifd == ifd && *number == tag)
+        .map(|(_, _, name, _)| *name)
+}
+
+/// Looks up a well-known tag by name, ignoring case.
+pub fn tag_by_name(name: &str) -> Option<(Ifd, u16)> {
+    TAGS.iter()
+        .find(|(_, _, tag_name, _)| tag_name.eq_ignore_ascii_case(name))
+        .map(|(ifd, tag, _, _)| (*ifd, *tag))
+}
+
+fn default_type(ifd: Ifd, tag: u16) -> Option<u16> {
+    TAGS.iter()
+        .find(|(tag_ifd, number, _, _)| *tag_ifd == ifd && *number == tag)
+        .map(|(_, _, _, value_type)| *value_type)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// `II`, Intel byte order.
+    Little,
+    /// `MM`, Motorola byte order.
+    Big,
+}
+
+impl ByteOrder {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    fn u64(self, bytes: &[u8]) -> u64 {
+        let bytes: [u8; 8] = bytes[..8].try_into().unwrap();
+        match self {
+            ByteOrder::Little => u64::from_le_bytes(bytes),
+            ByteOrder::Big => u64::from_be_bytes(bytes),
+        }
+    }
+
+    fn put_u16(self, value: u16) -> [u8; 2] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+
+    fn put_u32(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+
+    fn put_u64(self, value: u64) -> [u8; 8] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// The directory a tag lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ifd {
+    /// IFD0, describing the main image.
+    Primary,
+    Exif,
+    Gps,
+    Interop,
+    /// IFD1, describing the embedded thumbnail.
+    Thumbnail,
+}
+
+impl Display for Ifd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Ifd::Primary => "IFD0",
+            Ifd::Exif => "Exif",
+            Ifd::Gps => "GPS",
+            Ifd::Interop => "Interop",
+            Ifd::Thumbnail => "IFD1",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The value of a tag, one variant per TIFF field type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(Vec<u8>),
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    SByte(Vec<i8>),
+    Undefined(Vec<u8>),
+    SShort(Vec<i16>),
+    SLong(Vec<i32>),
+    SRational(Vec<(i32, i32)>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+fn parse_list<T: std::str::FromStr>(text: &str) -> Result<Vec<T>> {
+    text.split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .map_err(|_| Error::from(format!("invalid number {item:?}")))
+        })
+        .collect()
+}
+
+/// Parses `n/d`, a whole number or a decimal such as `2.8` into a fraction.
+fn parse_fraction(text: &str) -> Result<(i64, i64)> {
+    let invalid = || Error::from(format!("invalid fraction {text:?}"));
+    let text = text.trim();
+
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        let numerator = numerator.trim().parse().map_err(|_| invalid())?;
+        let denominator = denominator.trim().parse().map_err(|_| invalid())?;
+        return Ok((numerator, denominator));
+    }
+    match text.split_once('.') {
+        Some((whole, decimals)) if decimals.len() <= 6 => {
+            let numerator = format!("{whole}{decimals}")
+                .parse()
+                .map_err(|_| invalid())?;
+            Ok((numerator, 10i64.pow(decimals.len() as u32)))
+        }
+        Some(_) => Err(invalid()),
+        None => Ok((text.parse().map_err(|_| invalid())?, 1)),
+    }
+}
+
+impl Value {
+    fn type_code(&self) -> u16 {
+        match self {
+            Value::Byte(_) => BYTE,
+            Value::Ascii(_) => ASCII,
+            Value::Short(_) => SHORT,
+            Value::Long(_) => LONG,
+            Value::Rational(_) => RATIONAL,
+            Value::SByte(_) => SBYTE,
+            Value::Undefined(_) => UNDEFINED,
+            Value::SShort(_) => SSHORT,
+            Value::SLong(_) => SLONG,
+            Value::SRational(_) => SRATIONAL,
+            Value::Float(_) => FLOAT,
+            Value::Double(_) => DOUBLE,
+        }
+    }
+
+    fn type_size(type_code: u16) -> Option<usize> {
+        match type_code {
+            BYTE | ASCII | SBYTE | UNDEFINED => Some(1),
+            SHORT | SSHORT => Some(2),
+            LONG | SLONG | FLOAT | IFD => Some(4),
+            RATIONAL | SRATIONAL | DOUBLE => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Parses text into a value of the given TIFF type. Lists are comma
+    /// separated and rationals may be written as `n/d` or as decimals.
+    pub fn parse(type_code: u16, text: &str) -> Result<Value> {
+        let rational = |item: &str| -> Result<(u32, u32)> {
+            let (numerator, denominator) = parse_fraction(item)?;
+            Ok((u32::try_from(numerator)?, u32::try_from(denominator)?))
+        };
+        let signed_rational = |item: &str| -> Result<(i32, i32)> {
+            let (numerator, denominator) = parse_fraction(item)?;
+            Ok((i32::try_from(numerator)?, i32::try_from(denominator)?))
+        };
+
+        let value = match type_code {
+            BYTE => Value::Byte(parse_list(text)?),
+            ASCII => Value::Ascii(text.to_string()),
+            SHORT => Value::Short(parse_list(text)?),
+            LONG => Value::Long(parse_list(text)?),
+            RATIONAL => Value::Rational(text.split(',').map(rational).collect::<Result<_>>()?),
+            SBYTE => Value::SByte(parse_list(text)?),
+            UNDEFINED => Value::Undefined(text.as_bytes().to_vec()),
+            SSHORT => Value::SShort(parse_list(text)?),
+            SLONG => Value::SLong(parse_list(text)?),
+            SRATIONAL => Value::SRational(
+                text.split(',')
+                    .map(signed_rational)
+                    .collect::<Result<_>>()?,
+            ),
+            FLOAT => Value::Float(parse_list(text)?),
+            DOUBLE => Value::Double(parse_list(text)?),
+            _ => return Err(Error::from(format!("unknown field type {type_code}"))),
+        };
+        Ok(value)
+    }
+
+    fn decode(type_code: u16, bytes: &[u8], order: ByteOrder) -> Value {
+        let items = |size: usize| bytes.chunks_exact(size);
+        match type_code {
+            BYTE => Value::Byte(bytes.to_vec()),
+            ASCII => {
+                let text = bytes.split(|&byte| byte == 0).next().unwrap_or_default();
+                Value::Ascii(String::from_utf8_lossy(text).into_owned())
+            }
+            SHORT => Value::Short(items(2).map(|item| order.u16(item)).collect()),
+            LONG | IFD => Value::Long(items(4).map(|item| order.u32(item)).collect()),
+            RATIONAL => Value::Rational(
+                items(8)
+                    .map(|item| (order.u32(item), order.u32(&item[4..])))
+                    .collect(),
+            ),
+            SBYTE => Value::SByte(bytes.iter().map(|&byte| byte as i8).collect()),
+            SSHORT => Value::SShort(items(2).map(|item| order.u16(item) as i16).collect()),
+            SLONG => Value::SLong(items(4).map(|item| order.u32(item) as i32).collect()),
+            SRATIONAL => Value::SRational(
+                items(8)
+                    .map(|item| (order.u32(item) as i32, order.u32(&item[4..]) as i32))
+                    .collect(),
+            ),
+            FLOAT => Value::Float(
+                items(4)
+                    .map(|item| f32::from_bits(order.u32(item)))
+                    .collect(),
+            ),
+            DOUBLE => Value::Double(
+                items(8)
+                    .map(|item| f64::from_bits(order.u64(item)))
+                    .collect(),
+            ),
+            _ => Value::Undefined(bytes.to_vec()),
+        }
+    }
+
+    /// The element count stored in the IFD entry and the encoded bytes.
+    fn encode(&self, order: ByteOrder) -> (usize, Vec<u8>) {
+        fn flatten<T, const N: usize>(
+            items: &[T],
+            encode: impl Fn(&T) -> [u8; N],
+        ) -> (usize, Vec<u8>) {
+            (items.len(), items.iter().flat_map(encode).collect())
+        }
+
+        match self {
+            Value::Byte(bytes) | Value::Undefined(bytes) => (bytes.len(), bytes.clone()),
+            Value::Ascii(text) => {
+                let bytes: Vec<u8> = text.bytes().chain([0]).collect();
+                (bytes.len(), bytes)
+            }
+            Value::Short(items) => flatten(items, |item| order.put_u16(*item)),
+            Value::Long(items) => flatten(items, |item| order.put_u32(*item)),
+            Value::Rational(items) => flatten(items, |(numerator, denominator)| {
+                let mut bytes = [0; 8];
+                bytes[..4].copy_from_slice(&order.put_u32(*numerator));
+                bytes[4..].copy_from_slice(&order.put_u32(*denominator));
+                bytes
+            }),
+            Value::SByte(items) => flatten(items, |item| [*item as u8]),
+            Value::SShort(items) => flatten(items, |item| order.put_u16(*item as u16)),
+            Value::SLong(items) => flatten(items, |item| order.put_u32(*item as u32)),
+            Value::SRational(items) => flatten(items, |(numerator, denominator)| {
+                let mut bytes = [0; 8];
+                bytes[..4].copy_from_slice(&order.put_u32(*numerator as u32));
+                bytes[4..].copy_from_slice(&order.put_u32(*denominator as u32));
+                bytes
+            }),
+            Value::Float(items) => flatten(items, |item| order.put_u32(item.to_bits())),
+            Value::Double(items) => flatten(items, |item| order.put_u64(item.to_bits())),
+        }
+    }
+
+    fn as_u32(&self) -> Option<u32> {
+        match self {
+            Value::Short(items) => items.first().map(|&item| item as u32),
+            Value::Long(items) => items.first().copied(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn list<T: Display>(items: &[T]) -> String {
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        fn fractions<T: Display>(items: &[(T, T)]) -> String {
+            items
+                .iter()
+                .map(|(numerator, denominator)| format!("{numerator}/{denominator}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        let text = match self {
+            Value::Ascii(text) => text.clone(),
+            Value::Undefined(bytes)
+                if bytes.len() <= 64
+                    && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') =>
+            {
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+            Value::Byte(bytes) | Value::Undefined(bytes) if bytes.len() > 16 => {
+                format!("{} bytes", bytes.len())
+            }
+            Value::Byte(bytes) | Value::Undefined(bytes) => list(bytes),
+            Value::Short(items) => list(items),
+            Value::Long(items) => list(items),
+            Value::Rational(items) => fractions(items),
+            Value::SByte(items) => list(items),
+            Value::SShort(items) => list(items),
+            Value::SLong(items) => list(items),
+            Value::SRational(items) => fractions(items),
+            Value::Float(items) => list(items),
+            Value::Double(items) => list(items),
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// A single tag and its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub ifd: Ifd,
+    pub tag: u16,
+    pub value: Value,
+}
+
+impl Field {
+    pub fn name(&self) -> Option<&'static str> {
+        tag_name(self.ifd, self.tag)
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} {name}: {}", self.ifd, self.value),
+            None => write!(f, "{} {:#06x}: {}", self.ifd, self.tag, self.value),
+        }
+    }
+}
+
+/// The parsed contents of an eXIf chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exif {
+    pub byte_order: ByteOrder,
+    fields: Vec<Field>,
+    /// The JPEG thumbnail referenced from IFD1.
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+impl Default for Exif {
+    fn default() -> Self {
+        Exif {
+            byte_order: ByteOrder::Big,
+            fields: Vec::new(),
+            thumbnail: None,
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    order: ByteOrder,
+}
+
+impl Reader<'_> {
+    fn slice(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| Error::from(format!("EXIF data ends before offset {offset} + {len}")))
+    }
+
+    /// Reads the entries of the IFD at `offset` and the offset of the next
+    /// IFD.
+    fn ifd(&self, offset: usize) -> Result<(Vec<(u16, Value)>, u32)> {
+        let count = self.order.u16(self.slice(offset, 2)?) as usize;
+        let entries = self.slice(offset + 2, count * 12 + 4)?;
+
+        let mut fields = Vec::with_capacity(count);
+        for entry in entries.chunks_exact(12) {
+            let tag = self.order.u16(entry);
+            let type_code = self.order.u16(&entry[2..]);
+            let count = self.order.u32(&entry[4..]) as usize;
+            let Some(size) = Value::type_size(type_code).and_then(|size| size.checked_mul(count))
+            else {
+                // unknown types cannot be sized, so they are dropped
+                continue;
+            };
+
+            let bytes = if size <= 4 {
+                &entry[8..8 + size]
+            } else {
+                self.slice(self.order.u32(&entry[8..]) as usize, size)?
+            };
+            fields.push((tag, Value::decode(type_code, bytes, self.order)));
+        }
+
+        let next = self.order.u32(&entries[count * 12..]);
+        Ok((fields, next))
+    }
+}
+
+impl Exif {
+    pub fn parse(data: &[u8]) -> Result<Exif> {
+        let order = match data.get(..2) {
+            Some(b"II") => ByteOrder::Little,
+            Some(b"MM") => ByteOrder::Big,
+            _ => return Err(Error::from("EXIF data must start with II or MM")),
+        };
+        let reader = Reader { data, order };
+        if order.u16(reader.slice(2, 2)?) != 42 {
+            return Err(Error::from("missing TIFF magic number"));
+        }
+
+        let mut exif = Exif {
+            byte_order: order,
+            ..Exif::default()
+        };
+        let mut pending = vec![(Ifd::Primary, order.u32(reader.slice(4, 4)?))];
+        // each directory is followed once, however often it is pointed to
+        let mut queued = vec![Ifd::Primary];
+        let mut thumbnail = (None, None);
+
+        while let Some((ifd, offset)) = pending.pop() {
+            let (fields, next) = reader.ifd(offset as usize)?;
+            if exif.fields.len() + fields.len() > MAX_FIELDS {
+                return Err(Error::from(format!(
+                    "EXIF data has more than {MAX_FIELDS} fields"
+                )));
+            }
+
+            let mut pointers = Vec::new();
+            if ifd == Ifd::Primary && next != 0 {
+                pointers.push((Ifd::Thumbnail, next));
+            }
+            for (tag, value) in fields {
+                let pointer = value.as_u32();
+                match (ifd, tag, pointer) {
+                    (Ifd::Primary, EXIF_POINTER, Some(offset)) => {
+                        pointers.push((Ifd::Exif, offset))
+                    }
+                    (Ifd::Primary, GPS_POINTER, Some(offset)) => pointers.push((Ifd::Gps, offset)),
+                    (Ifd::Exif, INTEROP_POINTER, Some(offset)) => {
+                        pointers.push((Ifd::Interop, offset))
+                    }
+                    (Ifd::Thumbnail, THUMBNAIL_OFFSET, Some(offset)) => thumbnail.0 = Some(offset),
+                    (Ifd::Thumbnail, THUMBNAIL_LENGTH, Some(length)) => thumbnail.1 = Some(length),
+                    _ => exif.fields.push(Field { ifd, tag, value }),
+                }
+            }
+
+            for (ifd, offset) in pointers {
+                if !queued.contains(&ifd) {
+                    queued.push(ifd);
+                    pending.push((ifd, offset));
+                }
+            }
+        }
+
+        if let (Some(offset), Some(length)) = thumbnail {
+            let bytes = reader.slice(offset as usize, length as usize)?;
+            exif.thumbnail = Some(bytes.to_vec());
+        }
+        Ok(exif)
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn get(&self, ifd: Ifd, tag: u16) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|field| field.ifd == ifd && field.tag == tag)
+            .map(|field| &field.value)
+    }
+
+    /// Sets a tag, replacing any existing value.
+    pub fn set(&mut self, ifd: Ifd, tag: u16, value: Value) {
+        match self
+            .fields
+            .iter_mut()
+            .find(|field| field.ifd == ifd && field.tag == tag)
+        {
+            Some(field) => field.value = value,
+            None => self.fields.push(Field { ifd, tag, value }),
+        }
+    }
+
+    /// Parses `text` as the type of the existing value, or the usual type of
+    /// a well-known tag, and sets it.
+    pub fn set_text(&mut self, ifd: Ifd, tag: u16, text: &str) -> Result<()> {
+        let type_code = self
+            .get(ifd, tag)
+            .map(Value::type_code)
+            .or_else(|| default_type(ifd, tag))
+            .ok_or_else(|| format!("unknown type for {ifd} tag {tag:#06x}"))?;
+        self.set(ifd, tag, Value::parse(type_code, text)?);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, ifd: Ifd, tag: u16) -> Option<Value> {
+        let index = self
+            .fields
+            .iter()
+            .position(|field| field.ifd == ifd && field.tag == tag)?;
+        Some(self.fields.remove(index).value)
+    }
+
+    /// Removes every GPS tag, returning how many were removed.
+    pub fn strip_gps(&mut self) -> usize {
+        let before = self.fields.len();
+        self.fields.retain(|field| field.ifd != Ifd::Gps);
+        before - self.fields.len()
+    }
+
+    fn text(&self, ifd: Ifd, tag: u16) -> Option<&str> {
+        match self.get(ifd, tag)? {
+            Value::Ascii(text) => Some(text.trim()),
+            _ => None,
+        }
+    }
+
+    /// The orientation tag, 1 being upright and 2 to 8 mirrored or rotated.
+    pub fn orientation(&self) -> Option<u16> {
+        match self.get(Ifd::Primary, 0x0112)? {
+            Value::Short(items) => items.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// Camera make and model, joined by a space.
+    pub fn camera(&self) -> Option<String> {
+        let make = self.text(Ifd::Primary, 0x010f);
+        let model = self.text(Ifd::Primary, 0x0110);
+        match (make, model) {
+            (Some(make), Some(model)) if model.starts_with(make) => Some(model.to_string()),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (make, model) => make.or(model).map(str::to_string),
+        }
+    }
+
+    /// When the photo was taken, as `YYYY:MM:DD HH:MM:SS` local time.
+    pub fn date_time_original(&self) -> Option<&str> {
+        self.text(Ifd::Exif, 0x9003)
+            .or_else(|| self.text(Ifd::Primary, 0x0132))
+    }
+
+    /// Latitude and longitude in signed decimal degrees.
+    pub fn gps_position(&self) -> Option<(f64, f64)> {
+        let degrees = |tag: u16, reference: u16, negative: &str| {
+            let Value::Rational(parts) = self.get(Ifd::Gps, tag)? else {
+                return None;
+            };
+            let mut degrees = 0.0;
+            for ((numerator, denominator), scale) in parts.iter().zip([1.0, 60.0, 3600.0]) {
+                if *denominator == 0 {
+                    return None;
+                }
+                degrees += *numerator as f64 / *denominator as f64 / scale;
+            }
+            if self.text(Ifd::Gps, reference) == Some(negative) {
+                degrees = -degrees;
+            }
+            Some(degrees)
+        };
+
+        Some((degrees(0x0002, 0x0001, "S")?, degrees(0x0004, 0x0003, "W")?))
+    }
+
+    /// Serialises the directories into a fresh TIFF structure.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let order = self.byte_order;
+        let layout = [
+            Ifd::Primary,
+            Ifd::Exif,
+            Ifd::Interop,
+            Ifd::Gps,
+            Ifd::Thumbnail,
+        ];
+        let mut ifds: Vec<(Ifd, Vec<(u16, Value)>)> = layout
+            .iter()
+            .map(|&ifd| {
+                let fields = self
+                    .fields
+                    .iter()
+                    .filter(|field| field.ifd == ifd)
+                    .map(|field| (field.tag, field.value.clone()))
+                    .collect();
+                (ifd, fields)
+            })
+            .collect();
+
+        // pointer values are filled in once the offsets are known
+        let pointer = || Value::Long(vec![0]);
+        if !ifds[2].1.is_empty() {
+            ifds[1].1.push((INTEROP_POINTER, pointer()));
+        }
+        if !ifds[1].1.is_empty() {
+            ifds[0].1.push((EXIF_POINTER, pointer()));
+        }
+        if !ifds[3].1.is_empty() {
+            ifds[0].1.push((GPS_POINTER, pointer()));
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            ifds[4].1.push((THUMBNAIL_OFFSET, pointer()));
+            ifds[4]
+                .1
+                .push((THUMBNAIL_LENGTH, Value::Long(vec![thumbnail.len() as u32])));
+        }
+        ifds.retain(|(ifd, fields)| *ifd == Ifd::Primary || !fields.is_empty());
+        for (_, fields) in &mut ifds {
+            fields.sort_by_key(|(tag, _)| *tag);
+        }
+
+        let mut offsets = Vec::new();
+        let mut offset = 8;
+        for (ifd, fields) in &ifds {
+            offsets.push((*ifd, offset));
+            offset += ifd_size(fields, order);
+        }
+        let offset_of = |wanted: Ifd| {
+            offsets
+                .iter()
+                .find(|(ifd, _)| *ifd == wanted)
+                .map(|(_, offset)| *offset as u32)
+        };
+        let thumbnail_offset = offset as u32;
+
+        for (ifd, fields) in &mut ifds {
+            for (tag, value) in fields.iter_mut() {
+                let target = match (*ifd, *tag) {
+                    (Ifd::Primary, EXIF_POINTER) => offset_of(Ifd::Exif),
+                    (Ifd::Primary, GPS_POINTER) => offset_of(Ifd::Gps),
+                    (Ifd::Exif, INTEROP_POINTER) => offset_of(Ifd::Interop),
+                    (Ifd::Thumbnail, THUMBNAIL_OFFSET) => Some(thumbnail_offset),
+                    _ => None,
+                };
+                if let Some(target) = target {
+                    *value = Value::Long(vec![target]);
+                }
+            }
+        }
+
+        let mut bytes = match order {
+            ByteOrder::Little => b"II".to_vec(),
+            ByteOrder::Big => b"MM".to_vec(),
+        };
+        bytes.extend(order.put_u16(42));
+        bytes.extend(order.put_u32(8));
+        for (ifd, fields) in &ifds {
+            let next = match ifd {
+                Ifd::Primary => offset_of(Ifd::Thumbnail).unwrap_or(0),
+                _ => 0,
+            };
+            write_ifd(&mut bytes, fields, order, next);
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            bytes.extend(thumbnail);
+        }
+        bytes
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::EXIF, self.to_bytes())
+    }
+}
+
+/// Bytes taken by an IFD including the values stored outside its entries,
+/// each padded to an even length.
+fn ifd_size(fields: &[(u16, Value)], order: ByteOrder) -> usize {
+    let data: usize = fields
+        .iter()
+        .map(|(_, value)| value.encode(order).1.len())
+        .filter(|&len| len > 4)
+        .map(|len| len + len % 2)
+        .sum();
+    2 + fields.len() * 12 + 4 + data
+}
+
+/// Appends an IFD at the current end of `bytes`, with the values too large
+/// for an entry stored right after it.
+fn write_ifd(bytes: &mut Vec<u8>, fields: &[(u16, Value)], order: ByteOrder, next: u32) {
+    let mut data_offset = bytes.len() + 2 + fields.len() * 12 + 4;
+    let mut data = Vec::new();
+
+    bytes.extend(order.put_u16(fields.len() as u16));
+    for (tag, value) in fields {
+        let (count, encoded) = value.encode(order);
+        bytes.extend(order.put_u16(*tag));
+        bytes.extend(order.put_u16(value.type_code()));
+        bytes.extend(order.put_u32(count as u32));
+        if encoded.len() <= 4 {
+            let mut inline = [0; 4];
+            inline[..encoded.len()].copy_from_slice(&encoded);
+            bytes.extend(inline);
+        } else {
+            bytes.extend(order.put_u32(data_offset as u32));
+            data_offset += encoded.len() + encoded.len() % 2;
+            data.extend(&encoded);
+            if encoded.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+    }
+    bytes.extend(order.put_u32(next));
+    bytes.extend(data);
+}
+
+impl TryFrom<&ChunkRef<'_>> for Exif {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        Exif::parse(chunk.data())
+    }
+}
+
+from_owned_chunk!(Exif);
+
+impl Display for Exif {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tags: {}", self.fields.len())?;
+        for field in &self.fields {
+            write!(f, "\n{field}")?;
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            write!(f, "\nThumbnail: {} bytes", thumbnail.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_exif(byte_order: ByteOrder) -> Exif {
+        let mut exif = Exif {
+            byte_order,
+            ..Exif::default()
+        };
+        exif.set(Ifd::Primary, 0x010f, Value::Ascii("Canon".to_string()));
+        exif.set(
+            Ifd::Primary,
+            0x0110,
+            Value::Ascii("Canon EOS R5".to_string()),
+        );
+        exif.set(Ifd::Primary, 0x0112, Value::Short(vec![6]));
+        exif.set(
+            Ifd::Exif,
+            0x9003,
+            Value::Ascii("2023:03:14 15:09:26".to_string()),
+        );
+        exif.set(Ifd::Exif, 0x9000, Value::Undefined(b"0232".to_vec()));
+        exif.set(Ifd::Interop, 0x0001, Value::Ascii("R98".to_string()));
+        exif.set(Ifd::Gps, 0x0001, Value::Ascii("N".to_string()));
+        exif.set(
+            Ifd::Gps,
+            0x0002,
+            Value::Rational(vec![(52, 1), (30, 1), (0, 1)]),
+        );
+        exif.set(Ifd::Gps, 0x0003, Value::Ascii("W".to_string()));
+        exif.set(
+            Ifd::Gps,
+            0x0004,
+            Value::Rational(vec![(1, 1), (15, 1), (36, 1)]),
+        );
+        exif.thumbnail = Some(vec![0xff, 0xd8, 0xff, 0xd9]);
+        exif
+    }
+
+    /// Fields come back grouped by directory, so compare them sorted.
+    fn sorted_fields(exif: &Exif) -> Vec<String> {
+        let mut fields: Vec<String> = exif.fields().iter().map(|f| f.to_string()).collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for byte_order in [ByteOrder::Little, ByteOrder::Big] {
+            let exif = testing_exif(byte_order);
+            let parsed = Exif::parse(&exif.to_bytes()).unwrap();
+            assert_eq!(sorted_fields(&parsed), sorted_fields(&exif));
+            assert_eq!(parsed.thumbnail, exif.thumbnail);
+            assert_eq!(parsed.to_bytes(), exif.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let exif = testing_exif(ByteOrder::Little);
+        assert_eq!(exif.orientation(), Some(6));
+        assert_eq!(exif.camera().as_deref(), Some("Canon EOS R5"));
+        assert_eq!(exif.date_time_original(), Some("2023:03:14 15:09:26"));
+
+        let (latitude, longitude) = exif.gps_position().unwrap();
+        assert_eq!(latitude, 52.5);
+        assert!((longitude + 1.26).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_edit_and_strip_gps() {
+        let mut exif = testing_exif(ByteOrder::Big);
+        exif.set_text(Ifd::Primary, 0x0112, "1").unwrap();
+        exif.set_text(Ifd::Exif, 0x829d, "2.8").unwrap();
+        assert!(exif.set_text(Ifd::Primary, 0x0112, "up").is_err());
+        assert!(exif.set_text(Ifd::Primary, 0x1234, "1").is_err());
+        assert_eq!(
+            exif.remove(Ifd::Primary, 0x010f).unwrap().to_string(),
+            "Canon"
+        );
+
+        assert_eq!(exif.strip_gps(), 4);
+        let parsed = Exif::parse(&exif.to_bytes()).unwrap();
+        assert_eq!(parsed.orientation(), Some(1));
+        assert_eq!(
+            parsed.get(Ifd::Exif, 0x829d),
+            Some(&Value::Rational(vec![(28, 10)]))
+        );
+        assert!(parsed.gps_position().is_none());
+        assert!(!parsed.to_bytes().windows(2).any(|w| w == [0x88, 0x25]));
+    }
+
+    #[test]
+    fn test_tag_names() {
+        assert_eq!(tag_by_name("orientation"), Some((Ifd::Primary, 0x0112)));
+        assert_eq!(tag_by_name("GPSLatitude"), Some((Ifd::Gps, 0x0002)));
+        assert_eq!(tag_name(Ifd::Gps, 0x0001), Some("GPSLatitudeRef"));
+        assert_eq!(
+            tag_name(Ifd::Interop, 0x0001),
+            Some("InteroperabilityIndex")
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(Exif::parse(b"XX\0*\0\0\0\x08").is_err());
+        assert!(Exif::parse(b"MM\0*\0\0\0\x08").is_err());
+        assert!(Exif::parse(b"MM\0\x2b\0\0\0\x08\0\0\0\0\0\0").is_err());
+
+        // an entry pointing past the end of the data
+        let mut bytes = b"MM\0*\0\0\0\x08\0\x01".to_vec();
+        bytes.extend([0x01, 0x0f, 0, 2, 0, 0, 0, 10, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert!(Exif::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_repeated_pointers_followed_once() {
+        let entry = |tag: u16, type_code: u16, count: u32, value: [u8; 4]| {
+            let mut entry = tag.to_be_bytes().to_vec();
+            entry.extend(type_code.to_be_bytes());
+            entry.extend(count.to_be_bytes());
+            entry.extend(value);
+            entry
+        };
+
+        // IFD0 at 8 points to the Exif IFD at 50 three times, which points
+        // to the Interoperability IFD at 92 twice
+        let mut bytes = b"MM\0*\0\0\0\x08\0\x03".to_vec();
+        for _ in 0..3 {
+            bytes.extend(entry(EXIF_POINTER, LONG, 1, 50u32.to_be_bytes()));
+        }
+        bytes.extend([0, 0, 0, 0, 0, 3]);
+        for _ in 0..2 {
+            bytes.extend(entry(INTEROP_POINTER, LONG, 1, 92u32.to_be_bytes()));
+        }
+        bytes.extend(entry(0x9000, UNDEFINED, 4, *b"0232"));
+        bytes.extend([0, 0, 0, 0, 0, 1]);
+        bytes.extend(entry(0x0001, ASCII, 4, *b"R98\0"));
+        bytes.extend([0, 0, 0, 0]);
+
+        let exif = Exif::parse(&bytes).unwrap();
+        assert_eq!(exif.fields().len(), 2);
+        assert_eq!(
+            exif.get(Ifd::Exif, 0x9000),
+            Some(&Value::Undefined(b"0232".to_vec()))
+        );
+        assert_eq!(
+            exif.get(Ifd::Interop, 0x0001),
+            Some(&Value::Ascii("R98".to_string()))
+        );
+    }
+}
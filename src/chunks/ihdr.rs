@@ -0,0 +1,306 @@
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+/// Colour types allowed by the IHDR chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(ColorType::Grayscale),
+            2 => Ok(ColorType::Rgb),
+            3 => Ok(ColorType::Indexed),
+            4 => Ok(ColorType::GrayscaleAlpha),
+            6 => Ok(ColorType::Rgba),
+            _ => Err(Error::from(format!("unknown colour type {value}"))),
+        }
+    }
+}
+
+impl ColorType {
+    /// Number of samples per pixel.
+    pub fn channels(&self) -> u8 {
+        match self {
+            ColorType::Grayscale | ColorType::Indexed => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+
+    /// Bit depths the specification allows for this colour type.
+    pub fn bit_depths(&self) -> &'static [u8] {
+        match self {
+            ColorType::Grayscale => &[1, 2, 4, 8, 16],
+            ColorType::Indexed => &[1, 2, 4, 8],
+            ColorType::Rgb | ColorType::GrayscaleAlpha | ColorType::Rgba => &[8, 16],
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorType::Grayscale => "greyscale",
+            ColorType::Rgb => "truecolour",
+            ColorType::Indexed => "indexed-colour",
+            ColorType::GrayscaleAlpha => "greyscale with alpha",
+            ColorType::Rgba => "truecolour with alpha",
+        };
+        write!(f, "{} ({name})", *self as u8)
+    }
+}
+
+/// The image header, always the first chunk of a PNG file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ihdr {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: ColorType,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+/// Starting column, starting row, column step and row step of the seven
+/// Adam7 passes.
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+impl Ihdr {
+    /// A non-interlaced header using the only compression and filter
+    /// methods there are.
+    pub fn new(width: u32, height: u32, bit_depth: u8, color_type: ColorType) -> Ihdr {
+        Ihdr {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    /// Lists every way the header breaks the specification.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.width == 0 || self.width > i32::MAX as u32 {
+            problems.push(format!("invalid width {}", self.width));
+        }
+        if self.height == 0 || self.height > i32::MAX as u32 {
+            problems.push(format!("invalid height {}", self.height));
+        }
+        if !self.color_type.bit_depths().contains(&self.bit_depth) {
+            problems.push(format!(
+                "bit depth {} is not allowed for colour type {}",
+                self.bit_depth, self.color_type
+            ));
+        }
+        if self.compression_method != 0 {
+            problems.push(format!(
+                "unknown compression method {}",
+                self.compression_method
+            ));
+        }
+        if self.filter_method != 0 {
+            problems.push(format!("unknown filter method {}", self.filter_method));
+        }
+        if self.interlace_method > 1 {
+            problems.push(format!(
+                "unknown interlace method {}",
+                self.interlace_method
+            ));
+        }
+
+        problems
+    }
+
+    /// Size of the filtered image data once the IDAT stream is inflated.
+    pub fn image_data_len(&self) -> u64 {
+        let bits_per_pixel = self.color_type.channels() as u64 * self.bit_depth as u64;
+        let pass_len = |width: u64, height: u64| {
+            if width == 0 || height == 0 {
+                return 0;
+            }
+            // every row starts with a filter type byte, and a malformed
+            // header can describe an image too large to count in bytes
+            let row_len = width.saturating_mul(bits_per_pixel).div_ceil(8) + 1;
+            height.saturating_mul(row_len)
+        };
+
+        let (width, height) = (self.width as u64, self.height as u64);
+        if self.interlace_method == 0 {
+            return pass_len(width, height);
+        }
+
+        ADAM7_PASSES
+            .iter()
+            .map(|&(x, y, dx, dy)| {
+                let (x, y, dx, dy) = (x as u64, y as u64, dx as u64, dy as u64);
+                pass_len(
+                    width.saturating_sub(x).div_ceil(dx),
+                    height.saturating_sub(y).div_ceil(dy),
+                )
+            })
+            .fold(0, u64::saturating_add)
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data: Vec<u8> = self
+            .width
+            .to_be_bytes()
+            .iter()
+            .chain(&self.height.to_be_bytes())
+            .chain(&[
+                self.bit_depth,
+                self.color_type as u8,
+                self.compression_method,
+                self.filter_method,
+                self.interlace_method,
+            ])
+            .cloned()
+            .collect();
+
+        Chunk::new(ChunkType::IHDR, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Ihdr {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data: &[u8; 13] = chunk
+            .data()
+            .try_into()
+            .map_err(|_| "IHDR data must be 13 bytes long")?;
+
+        Ok(Ihdr {
+            width: u32::from_be_bytes(data[0..4].try_into()?),
+            height: u32::from_be_bytes(data[4..8].try_into()?),
+            bit_depth: data[8],
+            color_type: ColorType::try_from(data[9])?,
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        })
+    }
+}
+
+from_owned_chunk!(Ihdr);
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Width: {}", self.width)?;
+        writeln!(f, "Height: {}", self.height)?;
+        writeln!(f, "Bit depth: {}", self.bit_depth)?;
+        writeln!(f, "Colour type: {}", self.color_type)?;
+        writeln!(f, "Compression method: {}", self.compression_method)?;
+        writeln!(f, "Filter method: {}", self.filter_method)?;
+        write!(f, "Interlace method: {}", self.interlace_method)?;
+        match self.interlace_method {
+            0 => write!(f, " (none)"),
+            1 => write!(f, " (Adam7)"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_ihdr() -> Ihdr {
+        Ihdr::new(50, 50, 8, ColorType::Rgba)
+    }
+
+    #[test]
+    fn test_ihdr_round_trip() {
+        let ihdr = testing_ihdr();
+        let chunk = ihdr.to_chunk();
+        assert_eq!(chunk.length(), 13);
+        assert_eq!(Ihdr::try_from(&chunk).unwrap(), ihdr);
+    }
+
+    #[test]
+    fn test_ihdr_crc() {
+        // same header as the dice image used in the png tests
+        let chunk = testing_ihdr().to_chunk();
+        assert_eq!(chunk.crc(), 0x1e3f88b1);
+    }
+
+    #[test]
+    fn test_ihdr_problems() {
+        assert!(testing_ihdr().problems().is_empty());
+
+        let mut ihdr = testing_ihdr();
+        ihdr.width = 0;
+        ihdr.bit_depth = 4;
+        ihdr.interlace_method = 2;
+        assert_eq!(ihdr.problems().len(), 3);
+    }
+
+    #[test]
+    fn test_image_data_len() {
+        let mut ihdr = testing_ihdr();
+        assert_eq!(ihdr.image_data_len(), 50 * (1 + 50 * 4));
+
+        ihdr.color_type = ColorType::Grayscale;
+        ihdr.bit_depth = 1;
+        ihdr.width = 9;
+        ihdr.height = 1;
+        assert_eq!(ihdr.image_data_len(), 1 + 2);
+
+        // a 1x1 interlaced image only has data in the first pass
+        ihdr.width = 1;
+        ihdr.interlace_method = 1;
+        assert_eq!(ihdr.image_data_len(), 2);
+
+        // a hostile header used to overflow the byte count
+        let huge = Ihdr {
+            width: u32::MAX,
+            height: u32::MAX,
+            bit_depth: 255,
+            interlace_method: 1,
+            ..testing_ihdr()
+        };
+        assert_eq!(huge.image_data_len(), u64::MAX);
+    }
+
+    #[test]
+    fn test_ihdr_bad_length() {
+        let chunk = Chunk::new(ChunkType::IHDR, vec![0; 12]);
+        assert!(Ihdr::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_bad_color_type() {
+        let mut data = testing_ihdr().to_chunk().data().to_vec();
+        data[9] = 5;
+        let chunk = Chunk::new(ChunkType::IHDR, data);
+        assert!(Ihdr::try_from(&chunk).is_err());
+    }
+}
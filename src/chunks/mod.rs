@@ -0,0 +1,43 @@
+//! Typed views over the well-known chunk types.
+
+/// Implements `TryFrom<&Chunk>` for a typed view through its
+/// `TryFrom<&ChunkRef>`, so owned and borrowed chunks decode alike.
+macro_rules! from_owned_chunk {
+    ($view:ident) => {
+        impl TryFrom<&Chunk> for $view {
+            type Error = Error;
+
+            fn try_from(chunk: &Chunk) -> Result<Self> {
+                $view::try_from(&ChunkRef::from(chunk))
+            }
+        }
+    };
+}
+
+pub mod apng;
+pub mod color;
+pub mod exif;
+pub mod ihdr;
+pub mod palette;
+pub mod phys;
+pub mod text;
+pub mod time;
+pub mod xmp;
+
+use crate::chunk::ChunkRef;
+use crate::registry::{self, Registry};
+use crate::Result;
+
+/// Decodes the contents of a well-known chunk into a human readable form.
+///
+/// Returns `None` for chunk types this crate knows nothing about, and an
+/// error when a known chunk is malformed.
+pub fn describe(chunk: &ChunkRef) -> Option<Result<String>> {
+    describe_with(registry::standard(), chunk)
+}
+
+/// Like [`describe`], but knows the chunk types of `registry`, which may
+/// include private ones.
+pub fn describe_with(registry: &Registry, chunk: &ChunkRef) -> Option<Result<String>> {
+    registry.describe(chunk)
+}
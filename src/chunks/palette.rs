@@ -0,0 +1,333 @@
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::chunks::ihdr::{ColorType, Ihdr};
+use crate::{Error, Result};
+
+/// The colour palette stored in a PLTE chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    entries: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub const MAX_ENTRIES: usize = 256;
+
+    pub fn new(entries: Vec<[u8; 3]>) -> Result<Palette> {
+        if entries.is_empty() || entries.len() > Self::MAX_ENTRIES {
+            return Err(Error::from(format!(
+                "a palette needs between 1 and {} entries, not {}",
+                Self::MAX_ENTRIES,
+                entries.len()
+            )));
+        }
+        Ok(Palette { entries })
+    }
+
+    pub fn entries(&self) -> &[[u8; 3]] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<[u8; 3]> {
+        self.entries.get(index).copied()
+    }
+
+    pub fn set(&mut self, index: usize, rgb: [u8; 3]) -> Result<()> {
+        let len = self.entries.len();
+        let entry = self.entries.get_mut(index).ok_or_else(|| {
+            format!("palette index {index} out of range, there are {len} entries")
+        })?;
+        *entry = rgb;
+        Ok(())
+    }
+
+    pub fn push(&mut self, rgb: [u8; 3]) -> Result<()> {
+        if self.entries.len() == Self::MAX_ENTRIES {
+            return Err(Error::from("the palette is full"));
+        }
+        self.entries.push(rgb);
+        Ok(())
+    }
+
+    /// Checks that the palette is allowed for, and fits, the image.
+    pub fn validate(&self, ihdr: &Ihdr) -> Result<()> {
+        match ihdr.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Err(Error::from(format!(
+                "colour type {} does not allow a palette",
+                ihdr.color_type
+            ))),
+            // the bit depth is capped so that an invalid one cannot overflow
+            ColorType::Indexed if self.entries.len() > 1 << ihdr.bit_depth.min(8) => {
+                Err(Error::from(format!(
+                    "{} palette entries do not fit a bit depth of {}",
+                    self.entries.len(),
+                    ihdr.bit_depth
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::PLTE, self.entries.concat())
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Palette {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data = chunk.data();
+        if data.is_empty() || !data.len().is_multiple_of(3) {
+            return Err(Error::from("PLTE length must be a non-zero multiple of 3"));
+        }
+
+        let entries = data
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect();
+
+        Palette::new(entries)
+    }
+}
+
+from_owned_chunk!(Palette);
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Entries: {}", self.entries.len())?;
+        for (index, [r, g, b]) in self.entries.iter().enumerate() {
+            write!(f, "\n{index:>3}: #{r:02x}{g:02x}{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Simple transparency from a tRNS chunk. Its layout depends on the colour
+/// type of the image, so it is parsed together with the IHDR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// Alpha values for the first palette entries, the rest are opaque.
+    Indexed(Vec<u8>),
+    /// The grey level that is fully transparent.
+    Grayscale(u16),
+    /// The red, green and blue sample values that are fully transparent.
+    Rgb(u16, u16, u16),
+}
+
+impl Transparency {
+    pub fn parse(chunk: &ChunkRef, ihdr: &Ihdr) -> Result<Transparency> {
+        let data = chunk.data();
+        let sample = |index: usize| u16::from_be_bytes([data[index], data[index + 1]]);
+
+        match ihdr.color_type {
+            ColorType::Indexed => Ok(Transparency::Indexed(data.to_vec())),
+            ColorType::Grayscale if data.len() == 2 => Ok(Transparency::Grayscale(sample(0))),
+            ColorType::Rgb if data.len() == 6 => {
+                Ok(Transparency::Rgb(sample(0), sample(2), sample(4)))
+            }
+            ColorType::Grayscale => Err(Error::from("greyscale tRNS data must be 2 bytes long")),
+            ColorType::Rgb => Err(Error::from("truecolour tRNS data must be 6 bytes long")),
+            ColorType::GrayscaleAlpha | ColorType::Rgba => Err(Error::from(format!(
+                "colour type {} does not allow a tRNS chunk",
+                ihdr.color_type
+            ))),
+        }
+    }
+
+    /// Alpha of a palette entry, entries without a tRNS value are opaque.
+    pub fn alpha(&self, index: usize) -> Option<u8> {
+        match self {
+            Transparency::Indexed(alphas) => Some(alphas.get(index).copied().unwrap_or(255)),
+            _ => None,
+        }
+    }
+
+    /// Sets the alpha of a palette entry, filling any entries before it
+    /// with opaque values.
+    pub fn set_alpha(&mut self, index: usize, alpha: u8) -> Result<()> {
+        let Transparency::Indexed(alphas) = self else {
+            return Err(Error::from(
+                "only indexed-colour transparency has alpha values",
+            ));
+        };
+        if index >= Palette::MAX_ENTRIES {
+            return Err(Error::from(format!("palette index {index} out of range")));
+        }
+        if alphas.len() <= index {
+            alphas.resize(index + 1, 255);
+        }
+        alphas[index] = alpha;
+        Ok(())
+    }
+
+    /// Checks the tRNS rules against the image header and, for indexed
+    /// images, the palette.
+    pub fn validate(&self, ihdr: &Ihdr, palette: Option<&Palette>) -> Result<()> {
+        let max_sample = (1u32 << ihdr.bit_depth.min(16)) - 1;
+        let check_sample = |sample: u16| {
+            if sample as u32 > max_sample {
+                return Err(Error::from(format!(
+                    "tRNS sample {sample} does not fit a bit depth of {}",
+                    ihdr.bit_depth
+                )));
+            }
+            Ok(())
+        };
+
+        match (self, ihdr.color_type) {
+            (Transparency::Indexed(alphas), ColorType::Indexed) => {
+                let entries = palette.ok_or("tRNS needs a PLTE chunk")?.len();
+                if alphas.len() > entries {
+                    return Err(Error::from(format!(
+                        "tRNS has {} entries but the palette only {entries}",
+                        alphas.len()
+                    )));
+                }
+                Ok(())
+            }
+            (Transparency::Grayscale(gray), ColorType::Grayscale) => check_sample(*gray),
+            (Transparency::Rgb(r, g, b), ColorType::Rgb) => {
+                check_sample(*r)?;
+                check_sample(*g)?;
+                check_sample(*b)
+            }
+            _ => Err(Error::from(format!(
+                "tRNS does not match colour type {}",
+                ihdr.color_type
+            ))),
+        }
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let data = match self {
+            Transparency::Indexed(alphas) => alphas.clone(),
+            Transparency::Grayscale(gray) => gray.to_be_bytes().to_vec(),
+            Transparency::Rgb(r, g, b) => [r, g, b].iter().flat_map(|s| s.to_be_bytes()).collect(),
+        };
+        Chunk::new(ChunkType::TRNS, data)
+    }
+}
+
+impl Display for Transparency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transparency::Indexed(alphas) => {
+                write!(f, "Alpha values: {}", alphas.len())?;
+                for (index, alpha) in alphas.iter().enumerate() {
+                    write!(f, "\n{index:>3}: {alpha}")?;
+                }
+                Ok(())
+            }
+            Transparency::Grayscale(gray) => write!(f, "Transparent grey: {gray}"),
+            Transparency::Rgb(r, g, b) => write!(f, "Transparent colour: {r}, {g}, {b}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_ihdr(color_type: ColorType, bit_depth: u8) -> Ihdr {
+        Ihdr::new(1, 1, bit_depth, color_type)
+    }
+
+    #[test]
+    fn test_palette_entries() {
+        let chunk = Chunk::new(ChunkType::PLTE, vec![255, 0, 0, 0, 255, 0]);
+        let palette = Palette::try_from(&chunk).unwrap();
+        assert_eq!(palette.entries(), &[[255, 0, 0], [0, 255, 0]]);
+    }
+
+    #[test]
+    fn test_palette_bad_length() {
+        let chunk = Chunk::new(ChunkType::PLTE, vec![255, 0, 0, 0]);
+        assert!(Palette::try_from(&chunk).is_err());
+
+        let chunk = Chunk::new(ChunkType::PLTE, vec![0; 257 * 3]);
+        assert!(Palette::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_palette_edit() {
+        let mut palette = Palette::new(vec![[0, 0, 0]]).unwrap();
+        palette.set(0, [1, 2, 3]).unwrap();
+        palette.push([4, 5, 6]).unwrap();
+        assert!(palette.set(2, [0, 0, 0]).is_err());
+
+        let chunk = palette.to_chunk();
+        assert_eq!(chunk.data(), &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(Palette::try_from(&chunk).unwrap(), palette);
+    }
+
+    #[test]
+    fn test_palette_validate() {
+        let palette = Palette::new(vec![[0, 0, 0]; 5]).unwrap();
+        assert!(palette
+            .validate(&testing_ihdr(ColorType::Indexed, 4))
+            .is_ok());
+        assert!(palette
+            .validate(&testing_ihdr(ColorType::Indexed, 2))
+            .is_err());
+        assert!(palette.validate(&testing_ihdr(ColorType::Rgb, 8)).is_ok());
+        assert!(palette
+            .validate(&testing_ihdr(ColorType::Grayscale, 8))
+            .is_err());
+        // an invalid bit depth must not overflow the entry limit
+        assert!(palette
+            .validate(&testing_ihdr(ColorType::Indexed, 252))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_transparency_parse() {
+        let ihdr = testing_ihdr(ColorType::Rgb, 8);
+        let chunk = Chunk::new(ChunkType::TRNS, vec![0, 1, 0, 2, 0, 3]);
+        let trns = Transparency::parse(&ChunkRef::from(&chunk), &ihdr).unwrap();
+        assert_eq!(trns, Transparency::Rgb(1, 2, 3));
+        assert_eq!(trns.to_chunk().data(), chunk.data());
+
+        let chunk = Chunk::new(ChunkType::TRNS, vec![0, 1]);
+        assert!(Transparency::parse(&ChunkRef::from(&chunk), &ihdr).is_err());
+        let ihdr = testing_ihdr(ColorType::Rgba, 8);
+        assert!(Transparency::parse(&ChunkRef::from(&chunk), &ihdr).is_err());
+    }
+
+    #[test]
+    fn test_transparency_validate() {
+        let ihdr = testing_ihdr(ColorType::Grayscale, 2);
+        assert!(Transparency::Grayscale(3).validate(&ihdr, None).is_ok());
+        assert!(Transparency::Grayscale(4).validate(&ihdr, None).is_err());
+
+        let ihdr = testing_ihdr(ColorType::Indexed, 8);
+        let palette = Palette::new(vec![[0, 0, 0]; 2]).unwrap();
+        let trns = Transparency::Indexed(vec![0, 128, 255]);
+        assert!(trns.validate(&ihdr, Some(&palette)).is_err());
+        assert!(trns.validate(&ihdr, None).is_err());
+
+        let ihdr = testing_ihdr(ColorType::Grayscale, 252);
+        assert!(Transparency::Grayscale(u16::MAX)
+            .validate(&ihdr, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_transparency_alpha() {
+        let mut trns = Transparency::Indexed(vec![0]);
+        trns.set_alpha(3, 128).unwrap();
+        assert_eq!(trns.to_chunk().data(), &[0, 255, 255, 128]);
+        assert_eq!(trns.alpha(3), Some(128));
+        assert_eq!(trns.alpha(10), Some(255));
+        assert!(Transparency::Grayscale(0).set_alpha(0, 0).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Display;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+const METRES_PER_INCH: f64 = 0.0254;
+
+/// Intended pixel size or aspect ratio from a pHYs chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Phys {
+    pub pixels_per_unit_x: u32,
+    pub pixels_per_unit_y: u32,
+    /// 0 when the unit is unknown (aspect ratio only), 1 for metres.
+    pub unit: u8,
+}
+
+impl Phys {
+    pub const UNIT_UNKNOWN: u8 = 0;
+    pub const UNIT_METRE: u8 = 1;
+
+    /// Square pixels at the given resolution, converted to pixels per metre.
+    /// Fails if that is more than the 2^31 - 1 a PNG integer can hold.
+    pub fn from_dpi(dpi: u32) -> Result<Phys> {
+        let pixels_per_metre = (dpi as f64 / METRES_PER_INCH).round();
+        if pixels_per_metre > i32::MAX as f64 {
+            return Err(Error::from(format!(
+                "{dpi} dpi is more than {} pixels per metre",
+                i32::MAX
+            )));
+        }
+        Ok(Phys {
+            pixels_per_unit_x: pixels_per_metre as u32,
+            pixels_per_unit_y: pixels_per_metre as u32,
+            unit: Phys::UNIT_METRE,
+        })
+    }
+
+    /// Horizontal and vertical resolution in dots per inch, if the unit is
+    /// known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        (self.unit == Phys::UNIT_METRE).then_some((
+            self.pixels_per_unit_x as f64 * METRES_PER_INCH,
+            self.pixels_per_unit_y as f64 * METRES_PER_INCH,
+        ))
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = self.pixels_per_unit_x.to_be_bytes().to_vec();
+        data.extend(self.pixels_per_unit_y.to_be_bytes());
+        data.push(self.unit);
+        Chunk::new(ChunkType::PHYS, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Phys {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data: &[u8; 9] = chunk
+            .data()
+            .try_into()
+            .map_err(|_| "pHYs data must be 9 bytes long")?;
+
+        if data[8] > Phys::UNIT_METRE {
+            return Err(Error::from(format!("unknown pHYs unit {}", data[8])));
+        }
+
+        Ok(Phys {
+            pixels_per_unit_x: u32::from_be_bytes(data[0..4].try_into()?),
+            pixels_per_unit_y: u32::from_be_bytes(data[4..8].try_into()?),
+            unit: data[8],
+        })
+    }
+}
+
+from_owned_chunk!(Phys);
+
+impl Display for Phys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.dpi() {
+            Some((dpi_x, dpi_y)) => write!(
+                f,
+                "Pixels per metre: {} x {} ({dpi_x:.0} x {dpi_y:.0} dpi)",
+                self.pixels_per_unit_x, self.pixels_per_unit_y,
+            ),
+            None => write!(
+                f,
+                "Aspect ratio: {} x {} (unit {})",
+                self.pixels_per_unit_x, self.pixels_per_unit_y, self.unit
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phys() {
+        let chunk = Chunk::new(ChunkType::PHYS, vec![0, 0, 14, 194, 0, 0, 14, 194, 1]);
+        let phys = Phys::try_from(&chunk).unwrap();
+        assert_eq!(phys.pixels_per_unit_x, 3778);
+        assert_eq!(phys.unit, 1);
+        assert_eq!(
+            phys.to_string(),
+            "Pixels per metre: 3778 x 3778 (96 x 96 dpi)"
+        );
+    }
+
+    #[test]
+    fn test_phys_from_dpi() {
+        let phys = Phys::from_dpi(300).unwrap();
+        assert_eq!(phys.pixels_per_unit_x, 11811);
+        assert_eq!(phys.dpi().unwrap().0.round(), 300.0);
+        assert_eq!(Phys::try_from(&phys.to_chunk()).unwrap(), phys);
+
+        assert!(Phys::from_dpi(54_000_000).is_ok());
+        assert!(Phys::from_dpi(55_000_000).is_err());
+        assert!(Phys::from_dpi(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_phys_bad_unit() {
+        let chunk = Chunk::new(ChunkType::PHYS, vec![0, 0, 0, 1, 0, 0, 0, 1, 2]);
+        assert!(Phys::try_from(&chunk).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+/// Textual data from a tEXt, zTXt or iTXt chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Text {
+    pub keyword: String,
+    pub text: String,
+    /// Set for iTXt chunks only.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub international: Option<International>,
+    /// Whether the text is stored zlib-compressed (zTXt, or iTXt with the
+    /// compression flag set).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compressed: bool,
+}
+
+/// The extra fields carried by an iTXt chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct International {
+    pub language_tag: String,
+    pub translated_keyword: String,
+}
+
+pub(crate) fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+/// Encodes `text` as Latin-1, failing on characters outside it.
+pub(crate) fn encode_latin1(text: &str) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| {
+            u8::try_from(c).map_err(|_| Error::from(format!("{c:?} is not a Latin-1 character")))
+        })
+        .collect()
+}
+
+/// Encodes a keyword, which must be 1 to 79 printable Latin-1 characters.
+pub(crate) fn encode_keyword(keyword: &str) -> Result<Vec<u8>> {
+    if keyword.is_empty() || keyword.chars().count() > 79 {
+        return Err(Error::from("a keyword must be 1 to 79 characters long"));
+    }
+    if keyword.chars().any(char::is_control) {
+        return Err(Error::from("a keyword must be printable Latin-1"));
+    }
+    encode_latin1(keyword)
+}
+
+pub(crate) fn split_null(bytes: &[u8]) -> Result<(&[u8], &[u8])> {
+    let index = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or("missing null separator")?;
+    Ok((&bytes[..index], &bytes[index + 1..]))
+}
+
+/// The most that compressed text or an ICC profile may inflate to, so a
+/// small chunk cannot expand into gigabytes.
+pub(crate) const INFLATE_LIMIT: usize = 64 << 20;
+
+pub(crate) fn inflate(bytes: &[u8]) -> Result<Vec<u8>> {
+    inflate_limited(bytes, INFLATE_LIMIT)
+}
+
+fn inflate_limited(bytes: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(bytes)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut inflated)?;
+    if inflated.len() > limit {
+        return Err(Error::from(format!(
+            "compressed data inflates to more than {limit} bytes"
+        )));
+    }
+    Ok(inflated)
+}
+
+pub(crate) fn deflate(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // writing into a Vec cannot fail
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+impl Text {
+    pub fn new(keyword: &str, text: &str) -> Text {
+        Text {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+            international: None,
+            compressed: false,
+        }
+    }
+
+    fn parse_text(data: &[u8]) -> Result<Text> {
+        let (keyword, text) = split_null(data)?;
+        Ok(Text::new(&latin1(keyword), &latin1(text)))
+    }
+
+    fn parse_compressed(data: &[u8]) -> Result<Text> {
+        let (keyword, rest) = split_null(data)?;
+        let (&method, compressed) = rest.split_first().ok_or("missing compression method")?;
+        if method != 0 {
+            return Err(Error::from(format!("unknown compression method {method}")));
+        }
+
+        let mut text = Text::new(&latin1(keyword), &latin1(&inflate(compressed)?));
+        text.compressed = true;
+        Ok(text)
+    }
+
+    fn parse_international(data: &[u8]) -> Result<Text> {
+        let (keyword, rest) = split_null(data)?;
+        let [flag, method, rest @ ..] = rest else {
+            return Err(Error::from("missing compression flag"));
+        };
+        let (language_tag, rest) = split_null(rest)?;
+        let (translated_keyword, text) = split_null(rest)?;
+
+        let compressed = *flag != 0;
+        if compressed && *method != 0 {
+            return Err(Error::from(format!("unknown compression method {method}")));
+        }
+        let text = if compressed {
+            String::from_utf8(inflate(text)?)?
+        } else {
+            String::from_utf8(text.to_vec())?
+        };
+
+        Ok(Text {
+            keyword: latin1(keyword),
+            text,
+            international: Some(International {
+                language_tag: String::from_utf8(language_tag.to_vec())?,
+                translated_keyword: String::from_utf8(translated_keyword.to_vec())?,
+            }),
+            compressed,
+        })
+    }
+
+    /// Encodes the text into the chunk type matching its `international`
+    /// and `compressed` fields. Fails on an invalid keyword, on tEXt and
+    /// zTXt text that is not Latin-1, and on iTXt fields holding a null.
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = encode_keyword(&self.keyword)?;
+        data.push(0);
+
+        match (&self.international, self.compressed) {
+            (None, false) => {
+                data.extend(encode_latin1(&self.text)?);
+                Ok(Chunk::new(ChunkType::TEXT, data))
+            }
+            (None, true) => {
+                data.push(0);
+                data.extend(deflate(&encode_latin1(&self.text)?));
+                Ok(Chunk::new(ChunkType::ZTXT, data))
+            }
+            (Some(international), compressed) => {
+                let fields = [
+                    &international.language_tag,
+                    &international.translated_keyword,
+                ];
+                if fields.iter().any(|field| field.contains('\0')) {
+                    return Err(Error::from(
+                        "a language tag or translated keyword cannot contain a null",
+                    ));
+                }
+                data.extend([compressed as u8, 0]);
+                data.extend(international.language_tag.bytes().chain([0]));
+                data.extend(international.translated_keyword.bytes().chain([0]));
+                if compressed {
+                    data.extend(deflate(self.text.as_bytes()));
+                } else {
+                    data.extend(self.text.bytes());
+                }
+                Ok(Chunk::new(ChunkType::ITXT, data))
+            }
+        }
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Text {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        match *chunk.chunk_type() {
+            ChunkType::TEXT => Text::parse_text(chunk.data()),
+            ChunkType::ZTXT => Text::parse_compressed(chunk.data()),
+            ChunkType::ITXT => Text::parse_international(chunk.data()),
+            _ => Err(Error::from("not a text chunk")),
+        }
+    }
+}
+
+from_owned_chunk!(Text);
+
+impl Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Keyword: {}", self.keyword)?;
+        if let Some(international) = &self.international {
+            writeln!(f, "Language: {}", international.language_tag)?;
+            writeln!(
+                f,
+                "Translated keyword: {}",
+                international.translated_keyword
+            )?;
+        }
+        if self.compressed {
+            writeln!(f, "Compressed: yes")?;
+        }
+        write!(f, "Text: {}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_from_chunk() {
+        let chunk = Chunk::new(ChunkType::TEXT, b"Title\0A dice".to_vec());
+        let text = Text::try_from(&chunk).unwrap();
+        assert_eq!(text, Text::new("Title", "A dice"));
+    }
+
+    #[test]
+    fn test_text_missing_separator() {
+        let chunk = Chunk::new(ChunkType::TEXT, b"Title".to_vec());
+        assert!(Text::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_compressed_text_round_trip() {
+        let mut text = Text::new("Comment", "squeezed");
+        text.compressed = true;
+        let chunk = text.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type(), &ChunkType::ZTXT);
+        assert_eq!(Text::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_international_text_round_trip() {
+        for compressed in [false, true] {
+            let text = Text {
+                keyword: "Title".to_string(),
+                text: "Würfel".to_string(),
+                international: Some(International {
+                    language_tag: "de".to_string(),
+                    translated_keyword: "Titel".to_string(),
+                }),
+                compressed,
+            };
+            let chunk = text.to_chunk().unwrap();
+            assert_eq!(chunk.chunk_type(), &ChunkType::ITXT);
+            assert_eq!(Text::try_from(&chunk).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_text_is_rejected() {
+        for text in [
+            Text::new("", "empty keyword"),
+            Text::new(&"k".repeat(80), "long keyword"),
+            Text::new("Key\0word", "null in keyword"),
+            Text::new("Κλειδί", "keyword outside Latin-1"),
+            Text::new("Title", "text outside Latin-1: Würfel ✓"),
+        ] {
+            assert!(text.to_chunk().is_err(), "{text:?}");
+        }
+
+        // Latin-1 keywords are written as Latin-1, not UTF-8
+        let text = Text::new("Größe", "Würfel");
+        let chunk = text.to_chunk().unwrap();
+        assert_eq!(chunk.data(), b"Gr\xf6\xdfe\0W\xfcrfel");
+        assert_eq!(Text::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let compressed = deflate(&[0; 1000]);
+        assert_eq!(inflate_limited(&compressed, 1000).unwrap().len(), 1000);
+        assert!(inflate_limited(&compressed, 999).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Last modification time from a tIME chunk, always in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// Builds a time, rejecting fields outside their range. A second of 60
+    /// is allowed for leap seconds.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Result<Time> {
+        if !(1..=12).contains(&month) {
+            return Err(Error::from(format!("invalid month {month}")));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(Error::from(format!(
+                "invalid day {day} for {year:04}-{month:02}"
+            )));
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(Error::from(format!(
+                "invalid time of day {hour:02}:{minute:02}:{second:02}"
+            )));
+        }
+
+        Ok(Time {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    /// Converts seconds since the Unix epoch into a UTC calendar time.
+    pub fn from_unix(seconds: u64) -> Result<Time> {
+        let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+        // days to a civil date, after Howard Hinnant's `civil_from_days`
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+        Time::new(
+            u16::try_from(year).map_err(|_| "year does not fit a tIME chunk")?,
+            month as u8,
+            day as u8,
+            (seconds / 3600) as u8,
+            (seconds / 60 % 60) as u8,
+            (seconds % 60) as u8,
+        )
+    }
+
+    pub fn now() -> Result<Time> {
+        Time::from_unix(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = self.year.to_be_bytes().to_vec();
+        data.extend([self.month, self.day, self.hour, self.minute, self.second]);
+        Chunk::new(ChunkType::TIME, data)
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Time {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        let data: &[u8; 7] = chunk
+            .data()
+            .try_into()
+            .map_err(|_| "tIME data must be 7 bytes long")?;
+
+        Time::new(
+            u16::from_be_bytes([data[0], data[1]]),
+            data[2],
+            data[3],
+            data[4],
+            data[5],
+            data[6],
+        )
+    }
+}
+
+from_owned_chunk!(Time);
+
+/// Parses `YYYY-MM-DD HH:MM:SS`, also accepting a `T` between the date and
+/// the time and a trailing `Z`.
+impl FromStr for Time {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::from(format!("invalid time {s:?}, expected YYYY-MM-DD HH:MM:SS"));
+
+        let s = s.trim().trim_end_matches('Z');
+        let (date, time) = s.split_once(['T', ' ']).ok_or_else(invalid)?;
+        let date: Vec<&str> = date.split('-').collect();
+        let time: Vec<&str> = time.split(':').collect();
+        let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice())
+        else {
+            return Err(invalid());
+        };
+
+        let field = |value: &str| value.parse::<u8>().map_err(|_| invalid());
+        Time::new(
+            year.parse().map_err(|_| invalid())?,
+            field(month)?,
+            field(day)?,
+            field(hour)?,
+            field(minute)?,
+            field(second)?,
+        )
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Modified: {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time() {
+        let chunk = Chunk::new(ChunkType::TIME, vec![0x07, 0xe7, 3, 14, 15, 9, 26]);
+        let time = Time::try_from(&chunk).unwrap();
+        assert_eq!(time.to_string(), "Modified: 2023-03-14 15:09:26 UTC");
+        assert_eq!(time.to_chunk().data(), chunk.data());
+    }
+
+    #[test]
+    fn test_time_validation() {
+        assert!(Time::new(2024, 2, 29, 0, 0, 0).is_ok());
+        assert!(Time::new(2023, 2, 29, 0, 0, 0).is_err());
+        assert!(Time::new(1900, 2, 29, 0, 0, 0).is_err());
+        assert!(Time::new(2023, 13, 1, 0, 0, 0).is_err());
+        assert!(Time::new(2023, 12, 31, 23, 59, 60).is_ok());
+        assert!(Time::new(2023, 12, 31, 24, 0, 0).is_err());
+
+        let chunk = Chunk::new(ChunkType::TIME, vec![0x07, 0xe7, 0, 14, 15, 9, 26]);
+        assert!(Time::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_time_from_unix() {
+        assert_eq!(
+            Time::from_unix(0).unwrap(),
+            Time::new(1970, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            Time::from_unix(1_709_251_199).unwrap(),
+            Time::new(2024, 2, 29, 23, 59, 59).unwrap()
+        );
+        assert_eq!(
+            Time::from_unix(1_678_806_566).unwrap(),
+            Time::new(2023, 3, 14, 15, 9, 26).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_time_from_str() {
+        let expected = Time::new(2023, 3, 14, 15, 9, 26).unwrap();
+        assert_eq!(Time::from_str("2023-03-14 15:09:26").unwrap(), expected);
+        assert_eq!(Time::from_str("2023-03-14T15:09:26Z").unwrap(), expected);
+        assert!(Time::from_str("2023-03-14").is_err());
+        assert!(Time::from_str("2023-02-30 00:00:00").is_err());
+    }
+
+    #[test]
+    fn test_time_bad_length() {
+        let chunk = Chunk::new(ChunkType::TIME, vec![0x07, 0xe7, 3, 14]);
+        assert!(Time::try_from(&chunk).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! XMP packets, stored in an iTXt chunk with the keyword
+//! `XML:com.adobe.xmp`.
+
+use std::fmt::{Display, Write};
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::chunks::text::{International, Text};
+use crate::{Error, Result};
+
+pub const KEYWORD: &str = "XML:com.adobe.xmp";
+
+const PREDEFINED_ENTITIES: [&str; 5] = ["lt", "gt", "amp", "apos", "quot"];
+
+/// Whether a chunk holds an XMP packet.
+pub fn is_xmp(chunk: &Chunk) -> bool {
+    holds_xmp(&ChunkRef::from(chunk))
+}
+
+fn holds_xmp(chunk: &ChunkRef) -> bool {
+    chunk.chunk_type() == &ChunkType::ITXT
+        && chunk.data().starts_with(KEYWORD.as_bytes())
+        && chunk.data().get(KEYWORD.len()) == Some(&0)
+}
+
+/// Text and attribute values are kept escaped, exactly as they are written
+/// back out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+    /// A processing instruction or the XML declaration, without `<?` `?>`.
+    Instruction(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Result<Element> {
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            // only checks that the value is well-formed
+            attribute.normalized_value(XmlVersion::Implicit1_0)?;
+            attributes.push((
+                attribute.key.as_ref().to_string(),
+                attribute.value.into_owned(),
+            ));
+        }
+
+        Ok(Element {
+            name: start.name().as_ref().to_string(),
+            attributes,
+            children: Vec::new(),
+        })
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    fn find_all<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        if self.name == name {
+            found.push(self);
+        }
+        for element in self.elements() {
+            element.find_all(name, found);
+        }
+    }
+
+    fn find_mut(&mut self, name: &str) -> Option<&mut Element> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|node| match node {
+            Node::Element(element) => element.find_mut(name),
+            _ => None,
+        })
+    }
+
+    fn namespaces<'a>(&'a self, found: &mut Vec<&'a (String, String)>) {
+        found.extend(
+            self.attributes
+                .iter()
+                .filter(|(key, _)| key.starts_with("xmlns")),
+        );
+        for element in self.elements() {
+            element.namespaces(found);
+        }
+    }
+
+    fn set_attribute(&mut self, key: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(other, _)| other == key) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.attributes.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Writes `<name` and the attributes, quoting values that hold a
+    /// double quote with single quotes.
+    fn write_start(&self, out: &mut impl Write) -> std::fmt::Result {
+        write!(out, "<{}", self.name)?;
+        for (key, value) in &self.attributes {
+            let quote = if value.contains('"') { '\'' } else { '"' };
+            write!(out, " {key}={quote}{value}{quote}")?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, out: &mut String) {
+        // writing into a String cannot fail
+        self.write_start(out).unwrap();
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+        for child in &self.children {
+            child.serialize(out);
+        }
+        let _ = write!(out, "</{}>", self.name);
+    }
+
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(f, "{indent}")?;
+        self.write_start(f)?;
+
+        let children: Vec<&Node> = self
+            .children
+            .iter()
+            .filter(|node| !matches!(node, Node::Text(text) if text.trim().is_empty()))
+            .collect();
+        if children.is_empty() {
+            return writeln!(f, "/>");
+        }
+        if let [Node::Text(text)] = children.as_slice() {
+            return writeln!(f, ">{}</{}>", text.trim(), self.name);
+        }
+
+        writeln!(f, ">")?;
+        for child in children {
+            child.write(f, depth + 1)?;
+        }
+        writeln!(f, "{indent}</{}>", self.name)
+    }
+}
+
+impl Node {
+    /// Writes the node exactly as it was parsed, whitespace included.
+    fn serialize(&self, out: &mut String) {
+        match self {
+            Node::Element(element) => element.serialize(out),
+            Node::Text(text) => out.push_str(text),
+            Node::Comment(comment) => {
+                let _ = write!(out, "<!--{comment}-->");
+            }
+            Node::Instruction(instruction) => {
+                let _ = write!(out, "<?{instruction}?>");
+            }
+        }
+    }
+
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        match self {
+            Node::Element(element) => element.write(f, depth),
+            Node::Text(text) => writeln!(f, "{indent}{}", text.trim()),
+            Node::Comment(comment) => writeln!(f, "{indent}<!--{comment}-->"),
+            Node::Instruction(instruction) => writeln!(f, "{indent}<?{instruction}?>"),
+        }
+    }
+}
+
+/// A parsed, well-formed XMP packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xmp {
+    /// Top-level nodes: exactly one element, plus any `xpacket`
+    /// instructions and comments around it.
+    nodes: Vec<Node>,
+}
+
+impl Xmp {
+    /// Parses a packet, failing unless it is well-formed XML with a single
+    /// root element.
+    pub fn parse(text: &str) -> Result<Xmp> {
+        let mut reader = Reader::from_str(text);
+        let mut open: Vec<Element> = Vec::new();
+        let mut nodes = Vec::new();
+
+        loop {
+            let event = reader.read_event().map_err(|error| {
+                format!("malformed XML at byte {}: {error}", reader.error_position())
+            })?;
+            let node = match event {
+                Event::Start(start) => {
+                    open.push(Element::from_start(&start)?);
+                    continue;
+                }
+                Event::End(_) => Node::Element(open.pop().ok_or("unexpected end tag")?),
+                Event::Empty(start) => Node::Element(Element::from_start(&start)?),
+                Event::Text(text) => Node::Text(text.xml10_content().into_owned()),
+                Event::CData(data) => Node::Text(escape(data.xml10_content()).into_owned()),
+                Event::GeneralRef(reference) => {
+                    let known = match reference.resolve_char_ref()? {
+                        Some(_) => true,
+                        None => PREDEFINED_ENTITIES.contains(&&*reference),
+                    };
+                    if !known {
+                        return Err(Error::from(format!("undefined entity &{};", &*reference)));
+                    }
+                    Node::Text(format!("&{};", &*reference))
+                }
+                Event::Comment(comment) => Node::Comment(comment.xml10_content().into_owned()),
+                Event::PI(instruction) => Node::Instruction(instruction.to_string()),
+                Event::Decl(declaration) => Node::Instruction(declaration.to_string()),
+                Event::DocType(_) => return Err(Error::from("XMP packets cannot have a DOCTYPE")),
+                Event::Eof => break,
+            };
+
+            let siblings = match open.last_mut() {
+                Some(parent) => &mut parent.children,
+                None => &mut nodes,
+            };
+            // entity references split text into several events
+            match (siblings.last_mut(), node) {
+                (Some(Node::Text(text)), Node::Text(more)) => text.push_str(&more),
+                (_, node) => siblings.push(node),
+            }
+        }
+
+        if let Some(element) = open.last() {
+            return Err(Error::from(format!("unclosed element <{}>", element.name)));
+        }
+        for node in &nodes {
+            if matches!(node, Node::Text(text) if !text.trim().is_empty()) {
+                return Err(Error::from("text outside the root element"));
+            }
+        }
+        let roots = nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Element(_)))
+            .count();
+        if roots != 1 {
+            return Err(Error::from(format!(
+                "expected one root element, found {roots}"
+            )));
+        }
+
+        Ok(Xmp { nodes })
+    }
+
+    fn root(&self) -> &Element {
+        self.nodes
+            .iter()
+            .find_map(|node| match node {
+                Node::Element(element) => Some(element),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn root_mut(&mut self) -> &mut Element {
+        self.nodes
+            .iter_mut()
+            .find_map(|node| match node {
+                Node::Element(element) => Some(element),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    /// Merges the properties of `other` into this packet.
+    ///
+    /// Properties are the attributes and child elements of the
+    /// `rdf:Description` elements in `other`. They are added to the first
+    /// `rdf:Description` of this packet, replacing properties of the same
+    /// name. Namespace declarations are copied when the prefix is unknown.
+    pub fn merge(&mut self, other: &Xmp) -> Result<()> {
+        let mut declared = Vec::new();
+        self.root().namespaces(&mut declared);
+        let declared: Vec<String> = declared.iter().map(|(key, _)| key.clone()).collect();
+        let mut namespaces = Vec::new();
+        other.root().namespaces(&mut namespaces);
+
+        let mut descriptions = Vec::new();
+        other.root().find_all("rdf:Description", &mut descriptions);
+
+        let target = self
+            .root_mut()
+            .find_mut("rdf:Description")
+            .ok_or("the packet has no rdf:Description to merge into")?;
+
+        for (key, value) in namespaces {
+            if !declared.contains(key) {
+                target.set_attribute(key, value);
+            }
+        }
+        for description in descriptions {
+            for (key, value) in &description.attributes {
+                if key != "rdf:about" && !key.starts_with("xmlns") {
+                    target.children.retain(
+                        |node| !matches!(node, Node::Element(element) if element.name == *key),
+                    );
+                    target.set_attribute(key, value);
+                }
+            }
+            for property in description.elements() {
+                target.attributes.retain(|(key, _)| *key != property.name);
+                target.children.retain(
+                    |node| !matches!(node, Node::Element(element) if element.name == property.name),
+                );
+                target.children.push(Node::Element(property.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The packet as XML, keeping the whitespace and `xpacket` padding of
+    /// the parsed text. `Display` pretty-prints it instead.
+    fn serialize(&self) -> String {
+        let mut out = String::new();
+        for node in &self.nodes {
+            node.serialize(&mut out);
+        }
+        out
+    }
+
+    /// Stores the packet uncompressed, so that tools scanning files for XMP
+    /// can still find it.
+    pub fn to_chunk(&self) -> Chunk {
+        Text {
+            keyword: KEYWORD.to_string(),
+            text: self.serialize(),
+            international: Some(International::default()),
+            compressed: false,
+        }
+        .to_chunk()
+        // the keyword is valid and iTXt text can be any UTF-8
+        .unwrap()
+    }
+}
+
+impl TryFrom<&ChunkRef<'_>> for Xmp {
+    type Error = Error;
+
+    fn try_from(chunk: &ChunkRef) -> Result<Self> {
+        if !holds_xmp(chunk) {
+            return Err(Error::from("not an XMP chunk"));
+        }
+        Xmp::parse(&Text::try_from(chunk)?.text)
+    }
+}
+
+from_owned_chunk!(Xmp);
+
+/// Pretty-prints the packet, dropping whitespace between elements.
+impl Display for Xmp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            match node {
+                Node::Text(_) => {}
+                node => node.write(f, 0)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3">
+<xmp:CreatorTool>pngme &amp; friends</xmp:CreatorTool>
+</rdf:Description></rdf:RDF></x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    #[test]
+    fn test_pretty_print() {
+        let xmp = Xmp::parse(PACKET).unwrap();
+        let expected = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3">
+      <xmp:CreatorTool>pngme &amp; friends</xmp:CreatorTool>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#;
+        assert_eq!(xmp.to_string(), expected);
+        assert_eq!(Xmp::parse(expected).unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(Xmp::parse("<a><b></a>").is_err());
+        assert!(Xmp::parse("<a>").is_err());
+        assert!(Xmp::parse("<a/><b/>").is_err());
+        assert!(Xmp::parse("").is_err());
+        assert!(Xmp::parse("<a>&nbsp;</a>").is_err());
+        assert!(Xmp::parse("<a b='1' b='2'/>").is_err());
+        assert!(Xmp::parse("<a>&#169;</a>").is_ok());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut xmp = Xmp::parse(PACKET).unwrap();
+        let other = Xmp::parse(
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="other" xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+ xmlns:dc="http://purl.org/dc/elements/1.1/" xmp:CreatorTool="other tool">
+<xmp:Rating>5</xmp:Rating>
+<dc:format>image/png</dc:format>
+</rdf:Description></rdf:RDF>"#,
+        )
+        .unwrap();
+        xmp.merge(&other).unwrap();
+
+        let merged = xmp.to_string();
+        assert!(merged.contains(r#"rdf:about="""#));
+        assert!(merged.contains(r#"xmlns:dc="http://purl.org/dc/elements/1.1/""#));
+        assert!(merged.contains(r#"xmp:CreatorTool="other tool""#));
+        assert!(merged.contains("<xmp:Rating>5</xmp:Rating>"));
+        assert!(merged.contains("<dc:format>image/png</dc:format>"));
+        assert!(!merged.contains("xmp:Rating=\"3\""));
+        assert!(!merged.contains("friends"));
+    }
+
+    #[test]
+    fn test_chunk_round_trip() {
+        let xmp = Xmp::parse(PACKET).unwrap();
+        let chunk = xmp.to_chunk();
+        assert!(is_xmp(&chunk));
+        assert_eq!(Xmp::try_from(&chunk).unwrap(), xmp);
+        assert_eq!(Text::try_from(&chunk).unwrap().text, PACKET);
+
+        // whitespace, padding and quoting survive unchanged
+        let padded = PACKET
+            .replace(
+                "\n<?xpacket end",
+                &format!("\n{}\n<?xpacket end", " ".repeat(100)),
+            )
+            .replace("Rating=\"3\"", "Rating='\"3\"'");
+        let chunk = Xmp::parse(&padded).unwrap().to_chunk();
+        assert_eq!(Text::try_from(&chunk).unwrap().text, padded);
+
+        let other = Text::new("Comment", "<a/>").to_chunk().unwrap();
+        assert!(!is_xmp(&other));
+        assert!(Xmp::try_from(&other).is_err());
+    }
+}
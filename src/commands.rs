@@ -1,2 +1,609 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
 
+use pngme::check::{self, Severity};
+use pngme::chunk::{Chunk, ChunkRef};
+use pngme::chunk_type::ChunkType;
+use pngme::chunks::color::{self, ColorSpace, IccProfile};
+use pngme::chunks::exif::{self, Exif, Ifd};
+use pngme::chunks::phys::Phys;
+use pngme::chunks::time::Time;
+use pngme::chunks::xmp::{self, Xmp};
+use pngme::chunks::{self, apng};
+use pngme::diff;
+use pngme::hexdump::{hex_dump, hex_preview, PREVIEW_BYTES};
+use pngme::input::Input;
+use pngme::json;
+use pngme::png::{Png, PngRef};
+use pngme::registry::{self, Registry};
+use pngme::strip::{self, Policy, Strip};
+use pngme::{repair, Result};
 
+use crate::args::{ApngCommands, ExtractMode, Format, Method, StripPolicy, XmpCommands};
+
+fn read_png(file_path: &Path) -> Result<Png> {
+    let file = fs::read(file_path)?;
+    Png::try_from(file.as_slice())
+}
+
+fn write_png(file_path: &Path, png: &Png) -> Result<()> {
+    for chunk in png.discarded_chunks() {
+        println!(
+            "Dropped {} chunk: not safe to copy after image data changed",
+            chunk.chunk_type()
+        );
+    }
+    let mut file = File::options().write(true).truncate(true).open(file_path)?;
+    file.write_all(png.as_bytes().as_ref())?;
+    Ok(())
+}
+
+pub fn encode(
+    file_path: &Path,
+    chunk_type: Option<&str>,
+    method: Method,
+    message: &str,
+) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    match (method, chunk_type) {
+        (Method::Chunk, Some(chunk_type)) => {
+            let chunk_type = ChunkType::from_str(chunk_type)?;
+            let chunk = Chunk::new(chunk_type, message.as_bytes().to_vec());
+            png.append_chunk(chunk);
+        }
+        (Method::Chunk, None) => return Err("a chunk type is required".into()),
+        (Method::Trailer, Some(_)) => {
+            return Err("a chunk type cannot be used with the trailer method".into())
+        }
+        (Method::Trailer, None) => {
+            png.set_trailer(message.as_bytes().to_vec());
+        }
+    }
+
+    write_png(file_path, &png)
+}
+
+pub fn decode(file_path: &Path, chunk_type: Option<&str>, method: Method) -> Result<()> {
+    let file = Input::open(file_path)?;
+    let png = PngRef::try_from(&file[..])?;
+
+    let data = match (method, chunk_type) {
+        (Method::Chunk, Some(chunk_type)) => png
+            .chunk_by_type(chunk_type)
+            .ok_or("no such chunk in the specified file")?
+            .data(),
+        (Method::Chunk, None) => return Err("a chunk type is required".into()),
+        (Method::Trailer, Some(_)) => {
+            return Err("a chunk type cannot be used with the trailer method".into())
+        }
+        (Method::Trailer, None) if png.trailer().is_empty() => {
+            return Err("no data after IEND in the specified file".into())
+        }
+        (Method::Trailer, None) => png.trailer(),
+    };
+
+    println!("{}", String::from_utf8(data.to_vec())?);
+    Ok(())
+}
+
+pub fn remove(file_path: &Path, chunk_type: &str, keep_unsafe: bool) -> Result<()> {
+    let mut png = read_png(file_path)?;
+    png.set_keep_unsafe_to_copy(keep_unsafe);
+
+    if let Ok(chunk) = png.remove_chunk(chunk_type) {
+        println!("Removed chunk:\n\n {chunk}");
+        write_png(file_path, &png)?;
+    } else {
+        println!("no such chunk in the specified file")
+    };
+    Ok(())
+}
+
+fn chunk_types(names: &[String]) -> Result<Vec<ChunkType>> {
+    names
+        .iter()
+        .map(|name| Ok(ChunkType::from_str(name)?))
+        .collect()
+}
+
+pub fn strip(
+    file_path: &Path,
+    policy: Option<StripPolicy>,
+    keep: &[String],
+    drop: &[String],
+    trailer: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    let policy = policy.map(|policy| match policy {
+        StripPolicy::All => Policy::AllAncillary,
+        StripPolicy::Private => Policy::Private,
+        StripPolicy::UnsafeToCopy => Policy::UnsafeToCopy,
+    });
+    let removed = Strip::new(policy, chunk_types(keep)?, chunk_types(drop)?)?.apply(&mut png);
+    for chunk in &removed {
+        println!(
+            "Removed {} ({} bytes)",
+            chunk.chunk_type(),
+            chunk.length() + 12
+        );
+    }
+
+    let mut saved = strip::file_size(&removed);
+    if trailer {
+        let trailer = png.set_trailer(Vec::new());
+        if !trailer.is_empty() {
+            println!("Removed trailing data ({} bytes)", trailer.len());
+        }
+        saved += trailer.len();
+    }
+
+    println!("Saved {saved} bytes");
+    if saved > 0 && !dry_run {
+        write_png(file_path, &png)?;
+    }
+    Ok(())
+}
+
+pub fn print(file_path: &Path, verbose: bool, lenient: bool, format: Format) -> Result<()> {
+    let file = Input::open(file_path)?;
+    let png = if lenient {
+        PngRef::try_from_lenient(&file)?
+    } else {
+        PngRef::try_from(&file[..])?
+    };
+
+    let registry = registry::standard();
+    match format {
+        Format::Json => println!("{}", json::describe_with(registry, &png)),
+        Format::Text if verbose => print_verbose(registry, &png),
+        Format::Text => println!("{png}"),
+    }
+    Ok(())
+}
+
+fn print_verbose(registry: &Registry, png: &PngRef) {
+    for (index, (offset, chunk)) in png.chunks_with_offsets().enumerate() {
+        let chunk_type = chunk.chunk_type();
+
+        println!("Chunk #{index} {chunk_type} at offset {offset} ({offset:#x})");
+        if let Some(info) = registry.get(chunk_type) {
+            match info.section {
+                Some(section) => println!("  Name: {} (section {section})", info.name),
+                None => println!("  Name: {}", info.name),
+            }
+        }
+        println!("  Length: {}", chunk.length());
+        if chunk.has_valid_crc() {
+            println!("  Crc: {:#010x}", chunk.crc());
+        } else {
+            println!("  Crc: {:#010x} (invalid)", chunk.crc());
+        }
+        println!(
+            "  Properties: {}, {}, {}, {}",
+            if chunk_type.is_critical() {
+                "critical"
+            } else {
+                "ancillary"
+            },
+            if chunk_type.is_public() {
+                "public"
+            } else {
+                "private"
+            },
+            if chunk_type.is_reserved_bit_valid() {
+                "reserved bit valid"
+            } else {
+                "reserved bit invalid"
+            },
+            if chunk_type.is_safe_to_copy() {
+                "safe to copy"
+            } else {
+                "unsafe to copy"
+            },
+        );
+
+        // image data is only summarised, so it is not worth copying
+        let description = if chunk_type == &ChunkType::IDAT {
+            Some(Ok(format!(
+                "{} bytes of compressed image data",
+                chunk.length()
+            )))
+        } else {
+            chunks::describe_with(registry, &chunk)
+        };
+
+        let data_offset = offset + 8;
+        match description {
+            Some(Ok(description)) => {
+                for line in description.lines() {
+                    println!("  {line}");
+                }
+            }
+            Some(Err(error)) => {
+                println!("  Malformed: {error}");
+                print_preview(chunk.data(), data_offset);
+            }
+            None => print_preview(chunk.data(), data_offset),
+        }
+        println!();
+    }
+
+    if !png.trailer().is_empty() {
+        let offset = png.trailer_offset();
+        println!("Trailer at offset {offset} ({offset:#x})");
+        println!("  Length: {}", png.trailer().len());
+        print_preview(png.trailer(), offset);
+    }
+}
+
+fn print_preview(data: &[u8], data_offset: usize) {
+    if data.is_empty() {
+        return;
+    }
+    for line in hex_preview(data, data_offset, PREVIEW_BYTES).lines() {
+        println!("  {line}");
+    }
+}
+
+pub fn dump(file_path: &Path, chunk_type: &str, index: usize) -> Result<()> {
+    let file = Input::open(file_path)?;
+    let png = PngRef::try_from(&file[..])?;
+    let (offset, chunk) = png
+        .nth_chunk_by_type(chunk_type, index)
+        .ok_or("no such chunk in the specified file")?;
+
+    println!(
+        "Chunk {chunk_type} #{index} at offset {offset} ({offset:#x}), {} bytes of data",
+        chunk.length()
+    );
+    print!("{}", hex_dump(chunk.data(), offset + 8));
+    Ok(())
+}
+
+pub fn extract(
+    file_path: &Path,
+    chunk_type: &str,
+    index: usize,
+    output: &Path,
+    mode: ExtractMode,
+) -> Result<()> {
+    let file = Input::open(file_path)?;
+    let png = PngRef::try_from(&file[..])?;
+    let (_, chunk) = png
+        .nth_chunk_by_type(chunk_type, index)
+        .ok_or("no such chunk in the specified file")?;
+
+    let bytes = match mode {
+        ExtractMode::Data => chunk.data().to_vec(),
+        ExtractMode::Raw => chunk.as_bytes(),
+        ExtractMode::Chunk => chunk.to_owned().to_file_bytes(),
+    };
+    fs::write(output, &bytes)?;
+
+    println!("Wrote {} bytes to {}", bytes.len(), output.display());
+    Ok(())
+}
+
+pub fn inject(
+    file_path: &Path,
+    input: &Path,
+    index: Option<usize>,
+    keep_unsafe: bool,
+) -> Result<()> {
+    let mut png = read_png(file_path)?;
+    png.set_keep_unsafe_to_copy(keep_unsafe);
+    let chunk = Chunk::from_file_bytes(&fs::read(input)?)?;
+
+    println!("Injected chunk:\n\n {chunk}");
+    match index {
+        Some(index) => png.insert_chunk(index, chunk)?,
+        None => png.append_chunk(chunk),
+    }
+
+    write_png(file_path, &png)
+}
+
+/// Prints the validation report and returns the process exit code.
+pub fn check(file_path: &Path) -> Result<i32> {
+    let report = check::check(&Input::open(file_path)?);
+    println!("{report}");
+
+    Ok(match report.severity() {
+        None => 0,
+        Some(Severity::Warning) => 1,
+        Some(Severity::Error) => 2,
+    })
+}
+
+/// Prints the differences and returns the process exit code.
+pub fn diff(old: &Path, new: &Path, ignore: &[String]) -> Result<i32> {
+    let diff = diff::diff(&read_png(old)?, &read_png(new)?, &chunk_types(ignore)?);
+    println!("{diff}");
+    Ok(if diff.is_empty() { 0 } else { 1 })
+}
+
+pub fn repair(file_path: &Path, output: Option<&Path>, force: bool, dry_run: bool) -> Result<()> {
+    let repaired = repair::repair_with(&fs::read(file_path)?, force)?;
+    if repaired.fixes.is_empty() && repaired.skipped.is_empty() {
+        println!("Nothing to repair");
+        return Ok(());
+    }
+
+    if !repaired.fixes.is_empty() {
+        println!("Fixes:");
+        for fix in &repaired.fixes {
+            println!("  {fix}");
+        }
+    }
+    if !repaired.skipped.is_empty() {
+        println!("Skipped, rerun with --force to apply:");
+        for fix in &repaired.skipped {
+            println!("  {fix}");
+        }
+    }
+
+    if dry_run {
+        println!("Dry run, nothing written");
+        return Ok(());
+    }
+    if repaired.fixes.is_empty() {
+        println!("Nothing written");
+        return Ok(());
+    }
+
+    let output = output.unwrap_or(file_path);
+    fs::write(output, repaired.png.as_bytes())?;
+    println!("Wrote repaired file to {}", output.display());
+    Ok(())
+}
+
+pub fn color(file_path: &Path, strip: bool, icc: Option<&Path>, name: &str) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    if strip {
+        let removed = png.remove_chunks(|chunk| color::COLOR_CHUNKS.contains(chunk.chunk_type()));
+        println!("Removed {} colour space chunks", removed.len());
+        if !removed.is_empty() {
+            write_png(file_path, &png)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(icc) = icc {
+        let profile = IccProfile::new(name, fs::read(icc)?)?;
+        png.remove_chunks(|chunk| {
+            [ChunkType::SRGB, ChunkType::ICCP, ChunkType::CICP].contains(chunk.chunk_type())
+        });
+        png.set_chunk(profile.to_chunk()?);
+        write_png(file_path, &png)?;
+    }
+
+    println!("{}", ColorSpace::of(&png)?);
+    for chunk_type in ["mDCv", "cLLi"] {
+        if let Some(Some(description)) = png
+            .chunk_by_type(chunk_type)
+            .and_then(|chunk| chunks::describe(&ChunkRef::from(chunk)))
+            .map(Result::ok)
+        {
+            println!("{description}");
+        }
+    }
+    Ok(())
+}
+
+pub fn touch(file_path: &Path, time: Option<&str>) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    let time = match time {
+        Some(time) => Time::from_str(time)?,
+        None => Time::now()?,
+    };
+    png.set_chunk(time.to_chunk());
+    write_png(file_path, &png)?;
+
+    println!("{time}");
+    Ok(())
+}
+
+pub fn dpi(file_path: &Path, set: Option<u32>) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    if let Some(dpi) = set {
+        if dpi == 0 {
+            return Err("the resolution must be at least 1 dpi".into());
+        }
+        png.set_chunk(Phys::from_dpi(dpi)?.to_chunk());
+        write_png(file_path, &png)?;
+    }
+
+    match png.chunk_by_type("pHYs") {
+        Some(chunk) => println!("{}", Phys::try_from(chunk)?),
+        None => println!("no pHYs chunk in the specified file"),
+    }
+    Ok(())
+}
+
+fn exif_tag(name: &str) -> Result<(Ifd, u16)> {
+    exif::tag_by_name(name).ok_or_else(|| format!("unknown EXIF tag {name:?}").into())
+}
+
+pub fn exif(file_path: &Path, set: &[String], remove: &[String], strip_gps: bool) -> Result<()> {
+    let mut png = read_png(file_path)?;
+
+    let mut exif = match png.chunk_by_type("eXIf") {
+        Some(chunk) => Exif::try_from(chunk)?,
+        None => Exif::default(),
+    };
+    let edited = !set.is_empty() || !remove.is_empty() || strip_gps;
+
+    for assignment in set {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected TAG=VALUE, got {assignment:?}"))?;
+        let (ifd, tag) = exif_tag(name)?;
+        exif.set_text(ifd, tag, value)?;
+    }
+    for name in remove {
+        let (ifd, tag) = exif_tag(name)?;
+        if exif.remove(ifd, tag).is_none() {
+            println!("no {name} tag in the specified file");
+        }
+    }
+    if strip_gps {
+        println!("Removed {} GPS tags", exif.strip_gps());
+    }
+
+    if edited {
+        if exif.fields().is_empty() && exif.thumbnail.is_none() {
+            png.remove_chunks(|chunk| chunk.chunk_type() == &ChunkType::EXIF);
+        } else {
+            png.set_chunk(exif.to_chunk());
+        }
+        write_png(file_path, &png)?;
+    }
+
+    println!("{exif}");
+    if let Some(camera) = exif.camera() {
+        println!("Camera: {camera}");
+    }
+    if let Some(taken) = exif.date_time_original() {
+        println!("Taken: {taken}");
+    }
+    if let Some((latitude, longitude)) = exif.gps_position() {
+        println!("Location: {latitude:.6}, {longitude:.6}");
+    }
+    Ok(())
+}
+
+pub fn xmp(command: &XmpCommands) -> Result<()> {
+    match command {
+        XmpCommands::Get { file_path } => {
+            let png = read_png(file_path)?;
+            let chunk = png
+                .chunks()
+                .iter()
+                .find(|chunk| xmp::is_xmp(chunk))
+                .ok_or("no XMP packet in the specified file")?;
+            print!("{}", Xmp::try_from(chunk)?);
+        }
+        XmpCommands::Set {
+            file_path,
+            input,
+            merge,
+        } => {
+            let mut png = read_png(file_path)?;
+            let mut packet = Xmp::parse(&fs::read_to_string(input)?)?;
+
+            let existing = png.chunks().iter().position(xmp::is_xmp);
+            if let (Some(index), true) = (existing, *merge) {
+                let mut merged = Xmp::try_from(&png.chunks()[index])?;
+                merged.merge(&packet)?;
+                packet = merged;
+            }
+
+            png.remove_chunks(xmp::is_xmp);
+            match existing {
+                Some(index) => png.insert_chunk(index, packet.to_chunk())?,
+                None => png.append_chunk(packet.to_chunk()),
+            }
+            write_png(file_path, &png)?;
+            print!("{packet}");
+        }
+        XmpCommands::Remove { file_path } => {
+            let mut png = read_png(file_path)?;
+            if png.remove_chunks(xmp::is_xmp).is_empty() {
+                println!("no XMP packet in the specified file");
+            } else {
+                println!("Removed the XMP packet");
+                write_png(file_path, &png)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn apng(command: &ApngCommands) -> Result<()> {
+    match command {
+        ApngCommands::List { file_path } => {
+            let png = read_png(file_path)?;
+            for (index, frame) in png.frames()?.iter().enumerate() {
+                let control = &frame.control;
+                println!(
+                    "Frame #{index}: {}x{} at ({}, {}), delay {:.3}s, dispose {:?}, blend {:?}, {} bytes{}",
+                    control.width,
+                    control.height,
+                    control.x_offset,
+                    control.y_offset,
+                    control.delay(),
+                    control.dispose_op,
+                    control.blend_op,
+                    frame.data.len(),
+                    if frame.is_default_image {
+                        " (default image)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        ApngCommands::Extract {
+            file_path,
+            index,
+            output,
+        } => {
+            let png = read_png(file_path)?;
+            let frame = apng::extract_frame(&png, *index)?;
+            fs::write(output, frame.as_bytes())?;
+            println!("Wrote frame {index} to {}", output.display());
+        }
+        ApngCommands::Assemble {
+            output,
+            delay_ms,
+            plays,
+            frames,
+        } => {
+            let images = frames
+                .iter()
+                .map(|path| read_png(path))
+                .collect::<Result<Vec<Png>>>()?;
+            let png = apng::assemble(&images, (*delay_ms, 1000), *plays)?;
+            fs::write(output, png.as_bytes())?;
+            println!("Wrote {} frames to {}", images.len(), output.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "manifest")]
+pub fn build(manifest: &Path, output: &Path) -> Result<()> {
+    let png = pngme::manifest::read(manifest)?;
+    for warning in pngme::manifest::warnings(&png) {
+        eprintln!("Warning: {warning}");
+    }
+    let bytes = png.as_bytes();
+    fs::write(output, &bytes)?;
+    println!(
+        "Wrote {} chunks ({} bytes) to {}",
+        png.chunks().len(),
+        bytes.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+pub fn banner() {
+    println!(
+        "\n\n:::::::::  ::::    :::  ::::::::  ::::    ::::  :::::::::: 
+:+:    :+: :+:+:   :+: :+:    :+: +:+:+: :+:+:+ :+:        
++:+    +:+ :+:+:+  +:+ +:+        +:+ +:+:+ +:+ +:+        
++#++:++#+  +#+ +:+ +#+ :#:        +#+  +:+  +#+ +#++:++#   
++#+        +#+  +#+#+# +#+   +#+# +#+       +#+ +#+        
+#+#        #+#   #+#+# #+#    #+# #+#       #+# #+#        
+###        ###    ####  ########  ###       ### ########## \n\n"
+    )
+}
@@ -0,0 +1,218 @@
+//! A corpus of generated edge-case files with golden outputs for
+//! `print --lenient --format json` and `check`, so that behaviour changes
+//! show up as diffs under `testdata/conformance`.
+//!
+//! After an intended change, rewrite the golden files with
+//!
+//!     PNGME_BLESS=1 cargo test conformance
+//!
+//! and review the diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::check;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::chunks::ihdr::{ColorType, Ihdr};
+use crate::chunks::palette::Palette;
+use crate::chunks::text::{deflate, Text};
+use crate::json;
+use crate::png::{Png, PngRef};
+
+const COLOR_TYPES: [(ColorType, &[u8]); 5] = [
+    (ColorType::Grayscale, &[1, 2, 4, 8, 16]),
+    (ColorType::Rgb, &[8, 16]),
+    (ColorType::Indexed, &[1, 2, 4, 8]),
+    (ColorType::GrayscaleAlpha, &[8, 16]),
+    (ColorType::Rgba, &[8, 16]),
+];
+
+fn ihdr(color_type: ColorType, bit_depth: u8, interlace_method: u8) -> Ihdr {
+    Ihdr {
+        interlace_method,
+        ..Ihdr::new(5, 3, bit_depth, color_type)
+    }
+}
+
+/// A blank image: every scanline uses filter 0 and all samples are 0.
+fn image_data(ihdr: &Ihdr) -> Vec<u8> {
+    deflate(&vec![0; ihdr.image_data_len() as usize])
+}
+
+fn iend() -> Chunk {
+    Chunk::new(ChunkType::IEND, Vec::new())
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+fn image(color_type: ColorType, bit_depth: u8, interlace_method: u8) -> Vec<Chunk> {
+    let ihdr = ihdr(color_type, bit_depth, interlace_method);
+    let mut chunks = vec![ihdr.to_chunk()];
+    if color_type == ColorType::Indexed {
+        chunks.push(
+            Palette::new(vec![[0, 0, 0], [255, 255, 255]])
+                .unwrap()
+                .to_chunk(),
+        );
+    }
+    chunks.push(Chunk::new(ChunkType::IDAT, image_data(&ihdr)));
+    chunks.push(iend());
+    chunks
+}
+
+fn bytes(chunks: Vec<Chunk>) -> Vec<u8> {
+    Png::from_chunks(chunks).as_bytes()
+}
+
+/// Flips a bit of the crc of chunk `index`.
+fn corrupt_crc(mut bytes: Vec<u8>, index: usize) -> Vec<u8> {
+    let png = PngRef::try_from_lenient(&bytes).unwrap();
+    let chunk = png.chunk(index).unwrap();
+    let crc_offset = png.offsets()[index] + chunk.size() - 1;
+    bytes[crc_offset] ^= 1;
+    bytes
+}
+
+/// Every file in the corpus, named like the PngSuite files where there is
+/// an equivalent: `bas` + `n`/`i` for (non-)interlaced + colour type +
+/// bit depth.
+fn corpus() -> Vec<(String, Vec<u8>)> {
+    let mut corpus = Vec::new();
+    for (color_type, bit_depths) in COLOR_TYPES {
+        for &bit_depth in bit_depths {
+            for interlace_method in [0, 1] {
+                let name = format!(
+                    "bas{}{}{}{bit_depth:02}",
+                    ["n", "i"][interlace_method as usize],
+                    color_type as u8,
+                    match color_type {
+                        ColorType::Indexed => 'p',
+                        ColorType::Grayscale | ColorType::GrayscaleAlpha => 'g',
+                        ColorType::Rgb | ColorType::Rgba => 'c',
+                    },
+                );
+                corpus.push((name, bytes(image(color_type, bit_depth, interlace_method))));
+            }
+        }
+    }
+
+    let ihdr = ihdr(ColorType::Rgb, 8, 0);
+    let stream = image_data(&ihdr);
+
+    let mut chunks = image(ColorType::Rgb, 8, 0);
+    chunks.insert(1, chunk("tEXt", Vec::new()));
+    chunks.insert(1, chunk("zeRo", Vec::new()));
+    chunks.insert(3, Chunk::new(ChunkType::IDAT, Vec::new()));
+    corpus.push((String::from("zero_length"), bytes(chunks)));
+
+    let mut chunks = vec![ihdr.to_chunk()];
+    chunks.extend(
+        stream
+            .chunks(stream.len() / 3 + 1)
+            .map(|piece| Chunk::new(ChunkType::IDAT, piece.to_vec())),
+    );
+    chunks.push(iend());
+    corpus.push((String::from("multiple_idat"), bytes(chunks)));
+
+    let mut chunks = image(ColorType::Rgb, 8, 0);
+    chunks.insert(1, chunk("biGg", (0..=255).cycle().take(1 << 20).collect()));
+    corpus.push((String::from("large_chunk"), bytes(chunks)));
+
+    // the largest length the format allows, in a file that cannot hold it
+    let mut declared = bytes(image(ColorType::Rgb, 8, 0));
+    let iend_offset = declared.len() - 12;
+    declared[iend_offset..iend_offset + 4].copy_from_slice(&(i32::MAX as u32).to_be_bytes());
+    corpus.push((String::from("max_length_declared"), declared.clone()));
+    declared[iend_offset..iend_offset + 4].copy_from_slice(&(1u32 << 31).to_be_bytes());
+    corpus.push((String::from("length_too_large"), declared));
+
+    let mut chunks = image(ColorType::Rgb, 8, 0);
+    chunks.insert(1, chunk("prIv", b"unsafe to copy".to_vec()));
+    chunks.insert(1, chunk("prIV", b"safe to copy".to_vec()));
+    chunks.insert(3, chunk("PrIv", b"private and critical".to_vec()));
+    chunks.insert(4, chunk("prix", b"reserved bit set".to_vec()));
+    corpus.push((String::from("private_chunks"), bytes(chunks)));
+
+    let mut chunks = image(ColorType::Rgb, 8, 0);
+    chunks.insert(1, Text::new("Comment", "crc is wrong").to_chunk().unwrap());
+    let bad_crc = corrupt_crc(corrupt_crc(bytes(chunks), 1), 0);
+    corpus.push((String::from("bad_crc"), bad_crc));
+
+    let mut trailer = bytes(image(ColorType::Rgb, 8, 0));
+    trailer.extend_from_slice(b"data after the end of the image");
+    corpus.push((String::from("trailing_data"), trailer));
+
+    corpus
+}
+
+/// What `print --lenient --format json` writes for the file.
+fn print_json(bytes: &[u8]) -> String {
+    match PngRef::try_from_lenient(bytes) {
+        Ok(png) => format!("{}\n", json::describe(&png)),
+        Err(error) => format!("error: {error}\n"),
+    }
+}
+
+/// What `check` writes for the file.
+fn check(bytes: &[u8]) -> String {
+    format!("{}\n", check::check(bytes))
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/conformance")
+}
+
+#[test]
+fn test_conformance_corpus() {
+    let dir = golden_dir();
+    let bless = std::env::var_os("PNGME_BLESS").is_some();
+    if bless {
+        fs::create_dir_all(&dir).unwrap();
+    }
+
+    let mut expected_files = Vec::new();
+    let mut mismatches = Vec::new();
+    for (name, bytes) in corpus() {
+        for (extension, output) in [("json", print_json(&bytes)), ("check", check(&bytes))] {
+            let file_name = format!("{name}.{extension}");
+            let path = dir.join(&file_name);
+            if bless {
+                fs::write(&path, &output).unwrap();
+            } else if fs::read_to_string(&path).ok().as_ref() != Some(&output) {
+                mismatches.push(file_name.clone());
+            }
+            expected_files.push(file_name);
+        }
+    }
+
+    let mut stale: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| !expected_files.contains(name))
+        .collect();
+    stale.sort();
+    if bless {
+        for name in &stale {
+            fs::remove_file(dir.join(name)).unwrap();
+        }
+        return;
+    }
+
+    assert!(
+        mismatches.is_empty() && stale.is_empty(),
+        "outputs differ from testdata/conformance: {mismatches:?}, no longer generated: {stale:?}\n\
+         rerun with PNGME_BLESS=1 to update the golden files and review the diff"
+    );
+}
+
+#[test]
+fn test_corpus_is_valid() {
+    for (name, bytes) in corpus().iter().filter(|(name, _)| name.starts_with("bas")) {
+        let report = check::check(bytes);
+        assert!(report.issues.is_empty(), "{name}: {report}");
+    }
+}
@@ -0,0 +1,562 @@
+//! Comparison of the chunk structure of two files, for `diff`.
+//!
+//! Chunks are matched up by type and by how many chunks of that type come
+//! before them, with text chunks also matched on their keyword. Matched
+//! chunks that changed order are reported as moved, and ones whose data
+//! differs as modified.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::chunks::text::Text;
+use crate::png::Png;
+
+/// How many differing byte ranges are listed for a binary chunk.
+const MAX_RANGES: usize = 8;
+
+/// Texts whose line counts multiply to more than this are compared as
+/// bytes, as the line diff needs a table of that many entries.
+const MAX_LINE_PAIRS: usize = 1 << 20;
+
+/// Where a chunk is in one of the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// Position in the chunk list, counting from 0.
+    pub index: usize,
+    /// Offset of the chunk from the start of the file.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Removed(String),
+    Added(String),
+}
+
+/// How the data of a chunk changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Detail {
+    /// The lines of text that were removed and added.
+    Text(Vec<Line>),
+    /// Byte ranges, relative to the start of the data, that differ. Bytes
+    /// past the end of the shorter data count as differing.
+    Bytes {
+        old_len: usize,
+        new_len: usize,
+        ranges: Vec<Range<usize>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Added(Location),
+    Removed(Location),
+    Moved {
+        from: Location,
+        to: Location,
+    },
+    Modified {
+        old: Location,
+        new: Location,
+        detail: Detail,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub chunk_type: ChunkType,
+    /// The keyword of text chunks.
+    pub keyword: Option<String>,
+    pub kind: Kind,
+}
+
+/// The outcome of [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    /// How the data after IEND changed, with ranges relative to the start
+    /// of the trailer.
+    pub trailer: Option<Detail>,
+    old_trailer_offset: usize,
+    new_trailer_offset: usize,
+}
+
+impl Diff {
+    /// Whether the files have the same chunks, in the same order, and the
+    /// same trailer.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.trailer.is_none()
+    }
+}
+
+fn is_text(chunk_type: &ChunkType) -> bool {
+    matches!(&chunk_type.bytes(), b"tEXt" | b"zTXt" | b"iTXt")
+}
+
+/// What a chunk is matched on: its type, its keyword for text chunks, and
+/// how many chunks with the same type and keyword come before it.
+type Key = (ChunkType, Option<String>, usize);
+
+struct Entry<'a> {
+    key: Key,
+    location: Location,
+    chunk: &'a Chunk,
+}
+
+fn entries<'a>(png: &'a Png, ignore: &[ChunkType]) -> Vec<Entry<'a>> {
+    let mut seen: HashMap<(ChunkType, Option<String>), usize> = HashMap::new();
+    png.chunks_with_offsets()
+        .enumerate()
+        .filter(|(_, (_, chunk))| !ignore.contains(chunk.chunk_type()))
+        .map(|(index, (offset, chunk))| {
+            let chunk_type = *chunk.chunk_type();
+            let keyword = is_text(&chunk_type)
+                .then(|| Text::try_from(chunk).ok().map(|text| text.keyword))
+                .flatten();
+            let occurrence = seen.entry((chunk_type, keyword.clone())).or_default();
+            let key = (chunk_type, keyword, *occurrence);
+            *occurrence += 1;
+            Entry {
+                key,
+                location: Location { index, offset },
+                chunk,
+            }
+        })
+        .collect()
+}
+
+/// Ranges of positions where `old` and `new` differ.
+fn byte_ranges(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let common = old.len().min(new.len());
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for position in (0..common).filter(|&position| old[position] != new[position]) {
+        match ranges.last_mut() {
+            Some(range) if range.end == position => range.end += 1,
+            _ => ranges.push(position..position + 1),
+        }
+    }
+    if old.len() != new.len() {
+        let tail = common..old.len().max(new.len());
+        match ranges.last_mut() {
+            Some(range) if range.end == common => range.end = tail.end,
+            _ => ranges.push(tail),
+        }
+    }
+    ranges
+}
+
+fn bytes_detail(old: &[u8], new: &[u8]) -> Option<Detail> {
+    (old != new).then(|| Detail::Bytes {
+        old_len: old.len(),
+        new_len: new.len(),
+        ranges: byte_ranges(old, new),
+    })
+}
+
+/// The lines removed from `old` and added in `new`, from a longest common
+/// subsequence of their lines, or `None` if the texts have too many lines
+/// for that.
+fn line_diff(old: &str, new: &str) -> Option<Vec<Line>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    if (old.len() + 1).saturating_mul(new.len() + 1) > MAX_LINE_PAIRS {
+        return None;
+    }
+
+    // common[i][j] is the length of the lcs of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] > common[i + 1][j]) {
+            lines.push(Line::Added(new[j].to_string()));
+            j += 1;
+        } else {
+            lines.push(Line::Removed(old[i].to_string()));
+            i += 1;
+        }
+    }
+    Some(lines)
+}
+
+fn detail(old: &Chunk, new: &Chunk) -> Option<Detail> {
+    if old.data() == new.data() {
+        return None;
+    }
+    if is_text(old.chunk_type()) {
+        if let (Ok(old), Ok(new)) = (Text::try_from(old), Text::try_from(new)) {
+            let lines = line_diff(&old.text, &new.text);
+            // otherwise only the compression or the iTXt fields changed, or
+            // the texts are too long to compare line by line
+            if let Some(lines) = lines.filter(|lines| !lines.is_empty()) {
+                return Some(Detail::Text(lines));
+            }
+        }
+    }
+    bytes_detail(old.data(), new.data())
+}
+
+/// Positions in `sequence` that are part of one of its longest increasing
+/// subsequences.
+fn longest_increasing(sequence: &[usize]) -> Vec<bool> {
+    // tails[k] is the position of the smallest end of an increasing
+    // subsequence of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; sequence.len()];
+    for (position, value) in sequence.iter().enumerate() {
+        let length = tails.partition_point(|&tail| sequence[tail] < *value);
+        previous[position] = length.checked_sub(1).map(|before| tails[before]);
+        match tails.get_mut(length) {
+            Some(tail) => *tail = position,
+            None => tails.push(position),
+        }
+    }
+
+    let mut kept = vec![false; sequence.len()];
+    let mut position = tails.last().copied();
+    while let Some(current) = position {
+        kept[current] = true;
+        position = previous[current];
+    }
+    kept
+}
+
+/// Compares the chunks of two files, leaving out the chunk types in
+/// `ignore`.
+pub fn diff(old: &Png, new: &Png, ignore: &[ChunkType]) -> Diff {
+    let old_entries = entries(old, ignore);
+    let new_entries = entries(new, ignore);
+    let new_positions: HashMap<&Key, usize> = new_entries
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| (&entry.key, position))
+        .collect();
+    let old_keys: HashMap<&Key, usize> = old_entries
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| (&entry.key, position))
+        .collect();
+
+    let mut changes = Vec::new();
+    let change = |entry: &Entry, kind| Change {
+        chunk_type: entry.key.0,
+        keyword: entry.key.1.clone(),
+        kind,
+    };
+
+    // chunks found in both files, in the order of the old one
+    let matched: Vec<(&Entry, &Entry)> = old_entries
+        .iter()
+        .filter_map(|entry| {
+            new_positions
+                .get(&entry.key)
+                .map(|&position| (entry, &new_entries[position]))
+        })
+        .collect();
+    let order: Vec<usize> = matched
+        .iter()
+        .map(|(_, new)| new_positions[&new.key])
+        .collect();
+    let in_order = longest_increasing(&order);
+
+    for entry in &old_entries {
+        if !new_positions.contains_key(&entry.key) {
+            changes.push(change(entry, Kind::Removed(entry.location)));
+        }
+    }
+    for ((old_entry, new_entry), in_order) in matched.iter().zip(in_order) {
+        if let Some(detail) = detail(old_entry.chunk, new_entry.chunk) {
+            changes.push(change(
+                old_entry,
+                Kind::Modified {
+                    old: old_entry.location,
+                    new: new_entry.location,
+                    detail,
+                },
+            ));
+        } else if !in_order {
+            changes.push(change(
+                old_entry,
+                Kind::Moved {
+                    from: old_entry.location,
+                    to: new_entry.location,
+                },
+            ));
+        }
+    }
+    for entry in &new_entries {
+        if !old_keys.contains_key(&entry.key) {
+            changes.push(change(entry, Kind::Added(entry.location)));
+        }
+    }
+
+    Diff {
+        changes,
+        trailer: bytes_detail(old.trailer(), new.trailer()),
+        old_trailer_offset: old.trailer_offset(),
+        new_trailer_offset: new.trailer_offset(),
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} at offset {} ({:#x})",
+            self.index, self.offset, self.offset
+        )
+    }
+}
+
+/// Writes the lines of a detail, with byte ranges also given as offsets
+/// into both files.
+fn write_detail(
+    f: &mut std::fmt::Formatter<'_>,
+    detail: &Detail,
+    old_start: usize,
+    new_start: usize,
+) -> std::fmt::Result {
+    match detail {
+        Detail::Text(lines) => {
+            for line in lines {
+                match line {
+                    Line::Removed(line) => writeln!(f, "  - {line}")?,
+                    Line::Added(line) => writeln!(f, "  + {line}")?,
+                }
+            }
+        }
+        Detail::Bytes {
+            old_len,
+            new_len,
+            ranges,
+        } => {
+            if old_len != new_len {
+                writeln!(f, "  length {old_len} -> {new_len}")?;
+            }
+            for range in ranges.iter().take(MAX_RANGES) {
+                writeln!(
+                    f,
+                    "  bytes {}..{} differ (file offsets {:#x} and {:#x})",
+                    range.start,
+                    range.end,
+                    old_start + range.start,
+                    new_start + range.start
+                )?;
+            }
+            if ranges.len() > MAX_RANGES {
+                writeln!(f, "  ... {} more ranges", ranges.len() - MAX_RANGES)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            let name = match &change.keyword {
+                Some(keyword) => format!("{} {keyword:?}", change.chunk_type),
+                None => change.chunk_type.to_string(),
+            };
+            match &change.kind {
+                Kind::Added(location) => writeln!(f, "added {name} {location}")?,
+                Kind::Removed(location) => writeln!(f, "removed {name} {location}")?,
+                Kind::Moved { from, to } => writeln!(f, "moved {name} from {from} to {to}")?,
+                Kind::Modified { old, new, detail } => {
+                    writeln!(f, "modified {name} {old} -> {new}")?;
+                    write_detail(f, detail, old.offset + 8, new.offset + 8)?;
+                }
+            }
+        }
+        if let Some(detail) = &self.trailer {
+            writeln!(f, "modified trailer")?;
+            write_detail(f, detail, self.old_trailer_offset, self.new_trailer_offset)?;
+        }
+
+        let count = self.changes.len() + self.trailer.iter().count();
+        match count {
+            0 => write!(f, "no differences"),
+            1 => write!(f, "1 difference"),
+            count => write!(f, "{count} differences"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            Text::new("Title", "one\ntwo\nthree").to_chunk().unwrap(),
+            chunk("pHYs", &[0; 9]),
+            chunk("tIME", &[7, 232, 1, 1, 0, 0, 0]),
+            chunk("IDAT", &[1, 2, 3, 4, 5, 6]),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    fn kinds(diff: &Diff) -> Vec<String> {
+        diff.changes
+            .iter()
+            .map(|change| {
+                let kind = match change.kind {
+                    Kind::Added(_) => "added",
+                    Kind::Removed(_) => "removed",
+                    Kind::Moved { .. } => "moved",
+                    Kind::Modified { .. } => "modified",
+                };
+                format!("{kind} {}", change.chunk_type)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical() {
+        let diff = diff(&testing_png(), &testing_png(), &[]);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no differences");
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut old = testing_png();
+        old.set_trailer(b"before".to_vec());
+        let mut chunks = old.chunks().to_vec();
+        chunks[1] = Text::new("Title", "one\n2\nthree\nfour")
+            .to_chunk()
+            .unwrap();
+        chunks.swap(2, 3);
+        chunks[4] = chunk("IDAT", &[1, 0, 0, 4, 5, 6, 7]);
+        chunks.remove(0);
+        chunks.insert(0, chunk("IHDR", &[0; 13]));
+        chunks.insert(1, chunk("ruSt", b"new"));
+        let mut new = Png::from_chunks(chunks);
+        new.set_trailer(b"after!".to_vec());
+
+        let diff = diff(&old, &new, &[]);
+        assert_eq!(
+            kinds(&diff),
+            ["modified tEXt", "moved pHYs", "modified IDAT", "added ruSt"]
+        );
+        assert_eq!(
+            diff.changes[0].kind,
+            Kind::Modified {
+                old: Location {
+                    index: 1,
+                    offset: 33
+                },
+                new: Location {
+                    index: 2,
+                    offset: 48
+                },
+                detail: Detail::Text(vec![
+                    Line::Removed(String::from("two")),
+                    Line::Added(String::from("2")),
+                    Line::Added(String::from("four")),
+                ]),
+            }
+        );
+        let Kind::Modified { detail, .. } = &diff.changes[2].kind else {
+            panic!("IDAT not modified");
+        };
+        assert_eq!(
+            detail,
+            &Detail::Bytes {
+                old_len: 6,
+                new_len: 7,
+                ranges: vec![1..3, 6..7],
+            }
+        );
+        assert_eq!(
+            diff.trailer,
+            Some(Detail::Bytes {
+                old_len: 6,
+                new_len: 6,
+                ranges: vec![0..4, 5..6],
+            })
+        );
+
+        let text = diff.to_string();
+        assert!(text.contains("modified tEXt \"Title\" #1 at offset 33 (0x21) -> #2"));
+        assert!(text.contains("  - two\n  + 2\n  + four\n"));
+        assert!(text.contains("  length 6 -> 7\n  bytes 1..3 differ (file offsets"));
+        assert!(text.ends_with("5 differences"));
+    }
+
+    #[test]
+    fn test_removed_and_ignored() {
+        let old = testing_png();
+        let mut new = old.clone();
+        new.remove_chunk("pHYs").unwrap();
+        let mut chunks = new.chunks().to_vec();
+        chunks[2] = chunk("tIME", &[7, 233, 1, 1, 0, 0, 0]);
+        let new = Png::from_chunks(chunks);
+
+        assert_eq!(
+            kinds(&diff(&old, &new, &[])),
+            ["removed pHYs", "modified tIME"]
+        );
+        let ignore = [ChunkType::from_str("tIME").unwrap()];
+        assert_eq!(kinds(&diff(&old, &new, &ignore)), ["removed pHYs"]);
+    }
+
+    #[test]
+    fn test_text_keywords() {
+        let old = Png::from_chunks(vec![
+            Text::new("Author", "a").to_chunk().unwrap(),
+            Text::new("Title", "t").to_chunk().unwrap(),
+        ]);
+        let new = Png::from_chunks(vec![
+            Text::new("Title", "t").to_chunk().unwrap(),
+            Text::new("Comment", "c").to_chunk().unwrap(),
+        ]);
+        let diff = diff(&old, &new, &[]);
+        assert_eq!(kinds(&diff), ["removed tEXt", "added tEXt"]);
+        assert_eq!(diff.changes[0].keyword.as_deref(), Some("Author"));
+    }
+
+    #[test]
+    fn test_long_text_compared_as_bytes() {
+        let long: String = (0..2000).map(|line| format!("line {line}\n")).collect();
+        let old = Text::new("Comment", &long).to_chunk().unwrap();
+        let new = Text::new("Comment", &long.replace("line 1000\n", ""))
+            .to_chunk()
+            .unwrap();
+        assert!(matches!(detail(&old, &new), Some(Detail::Bytes { .. })));
+
+        let new = Text::new("Comment", "line 0\nline 1\n").to_chunk().unwrap();
+        assert!(matches!(detail(&old, &new), Some(Detail::Text(_))));
+    }
+
+    #[test]
+    fn test_longest_increasing() {
+        assert_eq!(
+            longest_increasing(&[0, 3, 1, 2, 4]),
+            [true, false, true, true, true]
+        );
+        assert!(longest_increasing(&[]).is_empty());
+    }
+}
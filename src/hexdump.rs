@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use crate::chunks::text::latin1;
+use crate::{Error, Result};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// How many bytes of an unknown chunk `print` shows.
+pub const PREVIEW_BYTES: usize = 64;
+
+/// Formats `data` as a canonical hex+ASCII dump in the style of `hexdump -C`.
+///
+/// `base_offset` is added to every printed offset so the dump can show
+/// positions relative to the start of the file rather than of the slice.
+pub fn hex_dump(data: &[u8], base_offset: usize) -> String {
+    let mut dump = String::new();
+
+    for (line, bytes) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let _ = write!(dump, "{:08x} ", base_offset + line * BYTES_PER_LINE);
+
+        for index in 0..BYTES_PER_LINE {
+            if index % 8 == 0 {
+                dump.push(' ');
+            }
+            match bytes.get(index) {
+                Some(byte) => {
+                    let _ = write!(dump, "{byte:02x} ");
+                }
+                None => dump.push_str("   "),
+            }
+        }
+
+        dump.push_str(" |");
+        dump.extend(bytes.iter().map(|&byte| printable(byte)));
+        dump.push_str("|\n");
+    }
+    let _ = writeln!(dump, "{:08x}", base_offset + data.len());
+
+    dump
+}
+
+/// Like [`hex_dump`], but only shows the first `limit` bytes of `data`.
+pub fn hex_preview(data: &[u8], base_offset: usize, limit: usize) -> String {
+    if data.len() <= limit {
+        return hex_dump(data, base_offset);
+    }
+
+    let mut preview = hex_dump(&data[..limit], base_offset);
+    let _ = writeln!(preview, "... {} more bytes", data.len() - limit);
+    preview
+}
+
+/// Formats `data` as lowercase hex digits without any separators.
+pub fn to_hex(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for byte in data {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Parses hex digits as written by [`to_hex`], in either case. Whitespace
+/// between bytes is ignored so long values can be wrapped.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(Error::from("hex data has an odd number of digits"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| match pair.iter().all(u8::is_ascii_hexdigit) {
+            // two hex digits are always valid utf-8 and fit a byte
+            true => Ok(u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap()),
+            false => Err(Error::from(format!("invalid hex byte {:?}", latin1(pair)))),
+        })
+        .collect()
+}
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump() {
+        let dump = hex_dump(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0", 0);
+        assert_eq!(
+            dump,
+            "00000000  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52  |.PNG........IHDR|\n\
+             00000010  00                                                |.|\n\
+             00000011\n"
+        );
+    }
+
+    #[test]
+    fn test_hex_dump_offset() {
+        let dump = hex_dump(b"abc", 0x21);
+        assert!(dump.starts_with("00000021  61 62 63"));
+        assert!(dump.ends_with("|abc|\n00000024\n"));
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let data = [0, 0x7f, 0x80, 0xff];
+        assert_eq!(to_hex(&data), "007f80ff");
+        assert_eq!(from_hex("007f80ff").unwrap(), data);
+        assert_eq!(from_hex("00 7F\n80 FF").unwrap(), data);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+        assert!(from_hex("+1").is_err());
+    }
+
+    #[test]
+    fn test_hex_preview() {
+        let preview = hex_preview(&[0; 40], 0, 16);
+        assert!(preview.ends_with("... 24 more bytes\n"));
+    }
+}
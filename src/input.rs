@@ -0,0 +1,79 @@
+//! Access to the bytes of an input file, memory-mapped where possible so
+//! that inspecting very large files does not read them whole.
+
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+pub enum Input {
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+    Buffer(Vec<u8>),
+}
+
+impl Input {
+    /// Maps the file into memory, falling back to reading it when mapping
+    /// is not supported (or the `mmap` feature is disabled).
+    ///
+    /// The mapping assumes the file is not changed while it is open, as is
+    /// the case for the inspection commands.
+    pub fn open(path: &Path) -> io::Result<Input> {
+        #[cfg(feature = "mmap")]
+        {
+            let file = fs::File::open(path)?;
+            // empty files cannot be mapped on every platform
+            if file.metadata()?.len() > 0 {
+                // SAFETY: the mapping is only read, and the file is expected
+                // not to be truncated by another process while it is mapped.
+                if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+                    return Ok(Input::Mapped(map));
+                }
+            }
+        }
+        Input::read(path)
+    }
+
+    /// Reads the whole file into memory.
+    pub fn read(path: &Path) -> io::Result<Input> {
+        fs::read(path).map(Input::Buffer)
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self, Input::Buffer(_))
+    }
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "mmap")]
+            Input::Mapped(map) => map,
+            Input::Buffer(bytes) => bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_read() {
+        let path = std::env::temp_dir().join(format!("pngme-input-{}", std::process::id()));
+        fs::write(&path, b"some bytes").unwrap();
+
+        let mapped = Input::open(&path).unwrap();
+        assert_eq!(&mapped[..], b"some bytes");
+        assert_eq!(mapped.is_mapped(), cfg!(feature = "mmap"));
+        let read = Input::read(&path).unwrap();
+        assert!(!read.is_mapped());
+        assert_eq!(&read[..], &mapped[..]);
+
+        fs::write(&path, b"").unwrap();
+        assert!(Input::open(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+//! The JSON description of a file written by `print --format json`.
+//!
+//! Only the little JSON needed for that is written here, so the output
+//! does not depend on any optional feature.
+
+use std::fmt::{Display, Write};
+
+use crate::chunk::ChunkRef;
+use crate::chunk_type::ChunkType;
+use crate::chunks;
+use crate::hexdump::{to_hex, PREVIEW_BYTES};
+use crate::png::PngRef;
+use crate::registry::{self, Registry};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    /// Fields are written in order.
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => {
+                let _ = write!(out, "{value}");
+            }
+            Json::Number(value) => {
+                let _ = write!(out, "{value}");
+            }
+            Json::String(value) => write_string(out, value),
+            Json::Array(values) if values.is_empty() => out.push_str("[]"),
+            Json::Array(values) => {
+                out.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    value.write(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push(']');
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push('{');
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// Pretty-printed with two spaces of indentation.
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        f.write_str(&out)
+    }
+}
+
+fn newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    out.extend(std::iter::repeat_n("  ", indent));
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// The first [`PREVIEW_BYTES`] bytes of `data` as lowercase hex.
+fn preview(data: &[u8]) -> Json {
+    Json::String(to_hex(&data[..data.len().min(PREVIEW_BYTES)]))
+}
+
+fn describe_chunk(registry: &Registry, index: usize, offset: usize, chunk: &ChunkRef) -> Json {
+    let chunk_type = chunk.chunk_type();
+    let mut fields = vec![
+        ("index", Json::Number(index as u64)),
+        ("offset", Json::Number(offset as u64)),
+        ("type", Json::String(chunk_type.to_string())),
+    ];
+    if let Some(info) = registry.get(chunk_type) {
+        fields.push(("name", Json::String(info.name.to_string())));
+    }
+    fields.extend([
+        ("length", Json::Number(chunk.length() as u64)),
+        ("crc", Json::Number(chunk.crc() as u64)),
+        ("crc_valid", Json::Bool(chunk.has_valid_crc())),
+        ("critical", Json::Bool(chunk_type.is_critical())),
+        ("public", Json::Bool(chunk_type.is_public())),
+        (
+            "reserved_bit_valid",
+            Json::Bool(chunk_type.is_reserved_bit_valid()),
+        ),
+        ("safe_to_copy", Json::Bool(chunk_type.is_safe_to_copy())),
+    ]);
+
+    // image data is only summarised, as in the text output
+    let description = if chunk_type == &ChunkType::IDAT {
+        Some(Ok(format!(
+            "{} bytes of compressed image data",
+            chunk.length()
+        )))
+    } else {
+        chunks::describe_with(registry, chunk)
+    };
+    match description {
+        Some(Ok(description)) => fields.push(("description", Json::String(description))),
+        Some(Err(error)) => {
+            fields.push(("error", Json::String(error.to_string())));
+            fields.push(("preview", preview(chunk.data())));
+        }
+        None => fields.push(("preview", preview(chunk.data()))),
+    }
+    Json::Object(fields)
+}
+
+/// Describes every chunk of the file, and the data after IEND if there is
+/// any. Known chunks are decoded, others get a hex preview of their data.
+pub fn describe(png: &PngRef) -> Json {
+    describe_with(registry::standard(), png)
+}
+
+/// Like [`describe`], but names and decodes the chunk types of `registry`.
+pub fn describe_with(registry: &Registry, png: &PngRef) -> Json {
+    let chunks = png
+        .chunks_with_offsets()
+        .enumerate()
+        .map(|(index, (offset, chunk))| describe_chunk(registry, index, offset, &chunk))
+        .collect();
+
+    let trailer = match png.trailer() {
+        [] => Json::Null,
+        trailer => Json::Object(vec![
+            ("offset", Json::Number(png.trailer_offset() as u64)),
+            ("length", Json::Number(trailer.len() as u64)),
+            ("preview", preview(trailer)),
+        ]),
+    };
+
+    Json::Object(vec![("chunks", Json::Array(chunks)), ("trailer", trailer)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::png::Png;
+    use crate::registry::ChunkInfo;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_json_format() {
+        let json = Json::Object(vec![
+            ("text", Json::String(String::from("a \"quote\"\n\u{1}"))),
+            (
+                "list",
+                Json::Array(vec![Json::Number(1), Json::Bool(false)]),
+            ),
+            ("empty", Json::Array(Vec::new())),
+            ("none", Json::Null),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            "{\n  \"text\": \"a \\\"quote\\\"\\n\\u0001\",\n  \"list\": [\n    1,\n    false\n  ],\n  \"empty\": [],\n  \"none\": null\n}"
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0xab; 100]),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]);
+        png.set_trailer(vec![1, 2]);
+        let bytes = png.as_bytes();
+        let json = describe(&PngRef::try_from(bytes.as_slice()).unwrap());
+
+        let Json::Object(fields) = json else {
+            panic!("not an object: {json}");
+        };
+        let Json::Array(chunks) = &fields[0].1 else {
+            panic!("no chunk list");
+        };
+        let Json::Object(rust) = &chunks[0] else {
+            panic!("not an object");
+        };
+        assert_eq!(rust[2], ("type", Json::String(String::from("ruSt"))));
+        assert_eq!(rust.last().unwrap(), &("preview", preview(&[0xab; 64])));
+        assert_eq!(
+            fields[1].1,
+            Json::Object(vec![
+                ("offset", Json::Number(8 + 112 + 12)),
+                ("length", Json::Number(2)),
+                ("preview", Json::String(String::from("0102"))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_describe_with_private_chunk() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let mut registry = Registry::default();
+        registry
+            .register(
+                ChunkInfo::new(chunk_type, "Rust level")
+                    .decoder(|chunk| Ok(format!("Level {}", chunk.data()[0]))),
+            )
+            .unwrap();
+        let bytes = Png::from_chunks(vec![
+            Chunk::new(chunk_type, vec![3]),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ])
+        .as_bytes();
+        let json = describe_with(&registry, &PngRef::try_from(bytes.as_slice()).unwrap());
+
+        let text = json.to_string();
+        assert!(text.contains("\"name\": \"Rust level\""), "{text}");
+        assert!(text.contains("\"description\": \"Level 3\""), "{text}");
+    }
+}
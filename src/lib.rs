@@ -0,0 +1,22 @@
+pub mod check;
+pub mod checksum;
+pub mod chunk;
+pub mod chunk_type;
+pub mod chunks;
+#[cfg(test)]
+mod conformance;
+pub mod diff;
+pub mod hexdump;
+pub mod input;
+pub mod json;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+pub mod png;
+pub mod registry;
+pub mod repair;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod strip;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
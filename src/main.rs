@@ -1,90 +1,121 @@
 mod args;
-mod chunk;
-mod chunk_type;
-// mod commands;
-mod png;
+mod commands;
 
-pub type Error = Box<dyn std::error::Error>;
-pub type Result<T> = std::result::Result<T, Error>;
-
-use std::fs::{self, File};
-use std::io::Write;
-use std::str::FromStr;
-
-use args::{Commands, CLI};
+use args::{Cli, Commands};
 use clap::Parser;
+use pngme::Result;
 
-use crate::chunk::Chunk;
-use crate::chunk_type::ChunkType;
-use crate::png::Png;
+/// The exit code of a command that reports its outcome through one. A
+/// failure is printed and mapped to `error_code`, so it cannot be mistaken
+/// for an outcome.
+fn exit_code(result: Result<i32>, error_code: i32) -> i32 {
+    result.unwrap_or_else(|error| {
+        eprintln!("Error: {error}");
+        error_code
+    })
+}
 
 fn main() -> Result<()> {
-    let cli = CLI::parse();
+    let cli = Cli::parse();
 
     match &cli.command {
         Commands::Encode {
             message,
             file_path,
             chunk_type,
-        } => {
-            let file = fs::read(file_path)?;
-            let mut png = Png::try_from(file.as_slice())?;
-            let chunk_type = ChunkType::from_str(chunk_type)?;
-            let chunk = Chunk::new(chunk_type, message.as_bytes().to_vec());
-            png.append_chunk(chunk);
-
-            let mut file = File::options().write(true).truncate(true).open(file_path)?;
-            file.write(png.as_bytes().as_ref())?;
-        }
+            method,
+        } => commands::encode(file_path, chunk_type.as_deref(), *method, message)?,
 
         Commands::Decode {
             file_path,
             chunk_type,
-        } => {
-            let file = fs::read(file_path)?;
-            let png = Png::try_from(file.as_slice())?;
+            method,
+        } => commands::decode(file_path, chunk_type.as_deref(), *method)?,
 
-            let chunk = png
-                .chunk_by_type(chunk_type)
-                .ok_or("no such chunk in the specified file")?;
+        Commands::Remove {
+            file_path,
+            chunk_type,
+            keep_unsafe,
+        } => commands::remove(file_path, chunk_type, *keep_unsafe)?,
 
-            println!("{}", chunk.data_as_string()?);
-        }
+        Commands::Print {
+            file_path,
+            verbose,
+            lenient,
+            format,
+        } => commands::print(file_path, *verbose, *lenient, *format)?,
 
-        Commands::Remove {
+        Commands::Dump {
             file_path,
             chunk_type,
-        } => {
-            // look into file reading and writing
-            let file = fs::read(file_path)?;
-            let mut png = Png::try_from(file.as_slice())?;
-
-            if let Ok(chunk) = png.remove_chunk(chunk_type) {
-                println!("Removed chunk:\n\n {chunk}");
-                let mut file = File::options().write(true).truncate(true).open(file_path)?;
-                file.write(png.as_bytes().as_ref())?;
-            } else {
-                println!("no such chunk in the specified file")
-            };
-        }
+            index,
+        } => commands::dump(file_path, chunk_type, *index)?,
 
-        Commands::Print { file_path } => {
-            let file = fs::read(file_path)?;
-            let png = Png::try_from(file.as_slice())?;
+        Commands::Extract {
+            file_path,
+            chunk_type,
+            index,
+            output,
+            mode,
+        } => commands::extract(file_path, chunk_type, *index, output, *mode)?,
+
+        Commands::Inject {
+            file_path,
+            input,
+            index,
+            keep_unsafe,
+        } => commands::inject(file_path, input, *index, *keep_unsafe)?,
 
-            println!("{png}")
+        Commands::Check { file_path } => {
+            std::process::exit(exit_code(commands::check(file_path), 3))
         }
-        Commands::Banner => {
-            println!(
-                "\n\n:::::::::  ::::    :::  ::::::::  ::::    ::::  :::::::::: 
-:+:    :+: :+:+:   :+: :+:    :+: +:+:+: :+:+:+ :+:        
-+:+    +:+ :+:+:+  +:+ +:+        +:+ +:+:+ +:+ +:+        
-+#++:++#+  +#+ +:+ +#+ :#:        +#+  +:+  +#+ +#++:++#   
-+#+        +#+  +#+#+# +#+   +#+# +#+       +#+ +#+        
-#+#        #+#   #+#+# #+#    #+# #+#       #+# #+#        
-###        ###    ####  ########  ###       ### ########## \n\n"
-            )
+
+        Commands::Diff { old, new, ignore } => {
+            std::process::exit(exit_code(commands::diff(old, new, ignore), 2))
         }
+
+        Commands::Repair {
+            file_path,
+            output,
+            force,
+            dry_run,
+        } => commands::repair(file_path, output.as_deref(), *force, *dry_run)?,
+
+        Commands::Strip {
+            file_path,
+            policy,
+            keep,
+            drop,
+            trailer,
+            dry_run,
+        } => commands::strip(file_path, *policy, keep, drop, *trailer, *dry_run)?,
+
+        Commands::Color {
+            file_path,
+            strip,
+            icc,
+            name,
+        } => commands::color(file_path, *strip, icc.as_deref(), name)?,
+
+        Commands::Touch { file_path, time } => commands::touch(file_path, time.as_deref())?,
+
+        Commands::Dpi { file_path, set } => commands::dpi(file_path, *set)?,
+
+        Commands::Exif {
+            file_path,
+            set,
+            remove,
+            strip_gps,
+        } => commands::exif(file_path, set, remove, *strip_gps)?,
+
+        Commands::Xmp { command } => commands::xmp(command)?,
+
+        Commands::Apng { command } => commands::apng(command)?,
+
+        #[cfg(feature = "manifest")]
+        Commands::Build { manifest, output } => commands::build(manifest, output)?,
+
+        Commands::Banner => commands::banner(),
     }
 
     Ok(())
@@ -0,0 +1,240 @@
+//! Manifests that describe a PNG file as a list of chunks, for `build`.
+//!
+//! A manifest uses the same form as the `serialize` module, so a serialized
+//! file is also a valid manifest, and adds a few ways of giving chunk data
+//! that are easier to write by hand:
+//!
+//! ```toml
+//! [[chunks]]
+//! ihdr = { width = 1, height = 1, bit_depth = 8, color_type = "Grayscale",
+//!          compression_method = 0, filter_method = 0, interlace_method = 0 }
+//!
+//! [[chunks]]
+//! text = { keyword = "Author", text = "pngme" }
+//!
+//! [[chunks]]
+//! type = "IDAT"
+//! file = "pixels.zlib"   # relative to the manifest
+//!
+//! [[chunks]]
+//! type = "ruSt"
+//! base64 = "c2VjcmV0"
+//!
+//! [[chunks]]
+//! type = "IEND"          # no data at all
+//! ```
+//!
+//! Lengths and crcs are always computed.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::chunk_type::ChunkType;
+use crate::hexdump::from_hex;
+use crate::png::Png;
+use crate::serialize::ChunkForm;
+use crate::{Error, Result};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    chunks: Vec<ChunkForm<'static>>,
+    /// Hex digits to store after IEND.
+    #[serde(default)]
+    trailer: Option<String>,
+}
+
+impl Manifest {
+    fn into_png(self, base_dir: &Path) -> Result<Png> {
+        let chunks = self
+            .chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, form)| {
+                form.into_chunk(Some(base_dir))
+                    .map_err(|error| Error::from(format!("chunk #{index}: {error}")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut png = Png::from_chunks(chunks);
+        if let Some(trailer) = &self.trailer {
+            png.set_trailer(from_hex(trailer)?);
+        }
+        Ok(png)
+    }
+}
+
+/// Builds a file from a TOML manifest. Data files are looked up relative to
+/// `base_dir`.
+pub fn from_toml(text: &str, base_dir: &Path) -> Result<Png> {
+    toml::from_str::<Manifest>(text)?.into_png(base_dir)
+}
+
+/// Builds a file from a JSON manifest. Data files are looked up relative to
+/// `base_dir`.
+pub fn from_json(text: &str, base_dir: &Path) -> Result<Png> {
+    serde_json::from_str::<Manifest>(text)?.into_png(base_dir)
+}
+
+/// Problems with the chunk list that `build` warns about. The file is still
+/// written, so that broken files can be built on purpose.
+pub fn warnings(png: &Png) -> Vec<String> {
+    let first = png.chunks().first().map(|chunk| *chunk.chunk_type());
+    let last = png.chunks().last().map(|chunk| *chunk.chunk_type());
+    let mut warnings = Vec::new();
+    match first {
+        None => warnings.push(String::from("the manifest has no chunks")),
+        Some(ChunkType::IHDR) => {}
+        Some(chunk_type) => warnings.push(format!("the first chunk is {chunk_type}, not IHDR")),
+    }
+    match last {
+        None | Some(ChunkType::IEND) => {}
+        Some(chunk_type) => warnings.push(format!("the last chunk is {chunk_type}, not IEND")),
+    }
+    warnings
+}
+
+/// Reads a manifest, as JSON if the file name ends in `.json` and as TOML
+/// otherwise.
+pub fn read(path: &Path) -> Result<Png> {
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => from_json(&text, base_dir),
+        _ => from_toml(&text, base_dir),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunks::ihdr::ColorType;
+    use crate::chunks::text::{deflate, Text};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_from_toml() {
+        let dir = std::env::temp_dir().join(format!("pngme-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pixels = deflate(&[0, 0]);
+        fs::write(dir.join("pixels.zlib"), &pixels).unwrap();
+        let manifest = r#"
+            trailer = "6869"
+
+            [[chunks]]
+            ihdr = { width = 1, height = 1, bit_depth = 8, color_type = "Grayscale", compression_method = 0, filter_method = 0, interlace_method = 0 }
+
+            [[chunks]]
+            text = { keyword = "Author", text = "pngme" }
+
+            [[chunks]]
+            type = "IDAT"
+            file = "pixels.zlib"
+
+            [[chunks]]
+            type = "ruSt"
+            base64 = "c2VjcmV0"
+
+            [[chunks]]
+            type = "ruSt"
+            data = "00ff"
+
+            [[chunks]]
+            type = "IEND"
+        "#;
+        fs::write(dir.join("manifest.toml"), manifest).unwrap();
+        let png = read(&dir.join("manifest.toml")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "tEXt", "IDAT", "ruSt", "ruSt", "IEND"]);
+        assert_eq!(png.ihdr().unwrap().color_type, ColorType::Grayscale);
+        assert_eq!(png.chunks()[2].data(), pixels);
+        assert_eq!(png.chunks()[3].data(), b"secret");
+        assert_eq!(png.chunks()[4].data(), &[0, 0xff]);
+        assert_eq!(png.trailer(), b"hi");
+        let report = crate::check::check(&png.as_bytes());
+        assert_eq!(report.count(crate::check::Severity::Error), 0, "{report}");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let png = from_json(
+            r#"{ "chunks": [{ "type": "IHDR", "data": "00" }, { "type": "IEND" }] }"#,
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(png.chunks()[0].data(), &[0]);
+        assert_eq!(png.chunks()[1], Chunk::new(ChunkType::IEND, Vec::new()));
+
+        // a serialized file is a manifest too
+        let mut original = Png::from_chunks(vec![
+            Text::new("Title", "round trip").to_chunk().unwrap(),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![1, 2, 3]),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]);
+        original.set_trailer(vec![0xff]);
+        let json = serde_json::to_string(&original).unwrap();
+        let png = from_json(&json, Path::new("")).unwrap();
+        assert_eq!(png.as_bytes(), original.as_bytes());
+
+        // files are only read relative to a manifest
+        let error =
+            serde_json::from_str::<Chunk>(r#"{ "type": "ruSt", "file": "x" }"#).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("only read from files in manifests"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let png = from_toml("chunks = []", Path::new("")).unwrap();
+        assert_eq!(warnings(&png), ["the manifest has no chunks"]);
+
+        let png = from_toml("[[chunks]]\ntype = \"ruSt\"", Path::new("")).unwrap();
+        assert_eq!(
+            warnings(&png),
+            [
+                "the first chunk is ruSt, not IHDR",
+                "the last chunk is ruSt, not IEND"
+            ]
+        );
+
+        let png = from_json(
+            r#"{ "chunks": [{ "type": "IHDR", "data": "00" }, { "type": "IEND" }] }"#,
+            Path::new(""),
+        )
+        .unwrap();
+        assert!(warnings(&png).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_errors() {
+        for (manifest, error) in [
+            (
+                "[[chunks]]\ndata = \"00\"",
+                "chunk #0: chunk without a type",
+            ),
+            (
+                "[[chunks]]\ntype = \"ruSt\"\ndata = \"00\"\nbase64 = \"AA==\"",
+                "given more than once",
+            ),
+            ("[[chunks]]\ntype = \"ruSt\"\nbase64 = \"!\"", "chunk #0: "),
+            (
+                "[[chunks]]\ntype = \"ruSt\"\nfile = \"missing.bin\"",
+                "cannot read missing.bin",
+            ),
+            ("[[chunks]]\ntype = \"ruSt\"\nsize = 1", "unknown field"),
+        ] {
+            let message = from_toml(manifest, Path::new("")).unwrap_err().to_string();
+            assert!(message.contains(error), "{manifest}: {message}");
+        }
+    }
+}
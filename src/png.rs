@@ -2,26 +2,116 @@ use std::fmt::Display;
 
 // QUESTION: implement first and last specific chunks?
 
-use crate::chunk::Chunk;
+use crate::chunk::{Chunk, ChunkRef};
+use crate::chunk_type::ChunkType;
+use crate::chunks::apng::{self, Frame};
+use crate::chunks::ihdr::Ihdr;
+use crate::chunks::palette::{Palette, Transparency};
+use crate::registry::{self, Placement, Registry};
 use crate::{Error, Result};
+
+/// The critical chunks that define the image itself.
+const IMAGE_CHUNKS: [ChunkType; 3] = [ChunkType::IHDR, ChunkType::PLTE, ChunkType::IDAT];
+
+#[derive(Debug, Clone)]
 pub struct Png {
     chunks: Vec<Chunk>,
+    /// Bytes found after the IEND chunk.
+    trailer: Vec<u8>,
+    /// Whether IHDR, PLTE or IDAT chunks were added, replaced or removed.
+    image_modified: bool,
+    /// Write unknown unsafe-to-copy chunks even after the image changed.
+    keep_unsafe_to_copy: bool,
+    /// Chunk types known besides the standard ones, if any.
+    registry: Option<Registry>,
 }
 
 impl Png {
-    const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Png {
+            chunks,
+            trailer: Vec::new(),
+            image_modified: false,
+            keep_unsafe_to_copy: false,
+            registry: None,
+        }
+    }
+
+    /// Like `Png::try_from`, but keeps chunks whose stored crc does not
+    /// match their contents.
+    pub fn try_from_lenient(value: &[u8]) -> Result<Png> {
+        Png::parse(value, true)
+    }
 
-    fn from_chunks(chunks: Vec<Chunk>) -> Self {
-        Png { chunks }
+    fn parse(value: &[u8], lenient: bool) -> Result<Png> {
+        PngRef::parse(value, lenient).map(|png| png.to_owned())
     }
 
+    fn note_change(&mut self, chunk_type: &ChunkType) {
+        if IMAGE_CHUNKS.contains(chunk_type) {
+            self.image_modified = true;
+        }
+    }
+
+    /// Adds the chunk before IEND, or at the end if there is no IEND.
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        // don't append at the end as it's after the IEND chunk
-        // self.chunks.push(chunk);
-        self.chunks.insert(self.chunks.len() - 1, chunk);
+        self.note_change(chunk.chunk_type());
+        let index = match self.chunks.last() {
+            Some(last) if last.chunk_type() == &ChunkType::IEND => self.chunks.len() - 1,
+            _ => self.chunks.len(),
+        };
+        self.chunks.insert(index, chunk);
+    }
+
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<()> {
+        if index > self.chunks.len() {
+            return Err(Error::from(format!(
+                "cannot insert at index {index}, png only has {} chunks",
+                self.chunks.len()
+            )));
+        }
+        self.note_change(chunk.chunk_type());
+        self.chunks.insert(index, chunk);
+        Ok(())
     }
 
-    // add append_chunk which will add at random place of the file
+    /// Replaces the first chunk of the same type, or inserts the chunk at
+    /// the position the specification requires for its type.
+    pub fn set_chunk(&mut self, chunk: Chunk) -> Option<Chunk> {
+        self.note_change(chunk.chunk_type());
+        let existing = self
+            .chunks
+            .iter()
+            .position(|other| other.chunk_type() == chunk.chunk_type());
+        if let Some(index) = existing {
+            return Some(std::mem::replace(&mut self.chunks[index], chunk));
+        }
+
+        let index = self.insert_position(chunk.chunk_type());
+        self.chunks.insert(index, chunk);
+        None
+    }
+
+    fn insert_position(&self, chunk_type: &ChunkType) -> usize {
+        let registry = self.registry();
+        let after_plte = |other: &ChunkType| registry.placement(other) == Placement::AfterPlte;
+        let goes_before = |other: &ChunkType| match registry.placement(chunk_type) {
+            _ if chunk_type == &ChunkType::PLTE => other == &ChunkType::IDAT || after_plte(other),
+            Placement::BeforePlte => {
+                [ChunkType::PLTE, ChunkType::IDAT].contains(other) || after_plte(other)
+            }
+            Placement::AfterPlte | Placement::BeforeIdat => other == &ChunkType::IDAT,
+            Placement::Anywhere => false,
+        };
+
+        let types = || self.chunks.iter().map(Chunk::chunk_type);
+        types()
+            .position(goes_before)
+            .or_else(|| types().position(|other| other == &ChunkType::IEND))
+            .unwrap_or(self.chunks.len())
+    }
 
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
         let index = self
@@ -30,79 +120,324 @@ impl Png {
             .position(|chunk| chunk.chunk_type().to_string() == chunk_type)
             .ok_or("no chunk with chunk_type {chunk_type} in png")?;
 
-        Ok(self.chunks.remove(index))
+        let chunk = self.chunks.remove(index);
+        self.note_change(chunk.chunk_type());
+        Ok(chunk)
+    }
+
+    /// Removes every chunk matching `predicate`, returning them in order.
+    pub fn remove_chunks(&mut self, mut predicate: impl FnMut(&Chunk) -> bool) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| predicate(chunk));
+        self.chunks = kept;
+        for chunk in &removed {
+            self.note_change(chunk.chunk_type());
+        }
+        removed
     }
 
     fn header(&self) -> &[u8; 8] {
         &Self::STANDARD_HEADER
     }
 
-    fn chunks(&self) -> &[Chunk] {
+    pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
     }
 
+    /// Pairs every chunk with its byte offset from the start of the file.
+    pub fn chunks_with_offsets(&self) -> impl Iterator<Item = (usize, &Chunk)> {
+        let mut offset = self.header().len();
+        self.chunks.iter().map(move |chunk| {
+            let chunk_offset = offset;
+            offset += chunk.length() as usize + 12;
+            (chunk_offset, chunk)
+        })
+    }
+
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         self.chunks
             .iter()
             .find(|chunk| chunk.chunk_type().to_string() == chunk_type)
     }
 
+    /// Finds the `nth` chunk of the given type, together with its byte
+    /// offset from the start of the file.
+    pub fn nth_chunk_by_type(&self, chunk_type: &str, nth: usize) -> Option<(usize, &Chunk)> {
+        self.chunks_with_offsets()
+            .filter(|(_, chunk)| chunk.chunk_type().to_string() == chunk_type)
+            .nth(nth)
+    }
+
+    pub fn ihdr(&self) -> Result<Ihdr> {
+        Ihdr::try_from(self.chunk_by_type("IHDR").ok_or("missing IHDR chunk")?)
+    }
+
+    pub fn palette(&self) -> Result<Option<Palette>> {
+        self.chunk_by_type("PLTE")
+            .map(Palette::try_from)
+            .transpose()
+    }
+
+    /// Replaces the PLTE chunk after checking the palette fits the image
+    /// and any tRNS chunk. IDAT is left untouched, so this is meant for
+    /// recolouring.
+    pub fn set_palette(&mut self, palette: &Palette) -> Result<()> {
+        let ihdr = self.ihdr()?;
+        palette.validate(&ihdr)?;
+        if let Some(transparency) = self.transparency()? {
+            transparency.validate(&ihdr, Some(palette))?;
+        }
+        self.set_chunk(palette.to_chunk());
+        Ok(())
+    }
+
+    pub fn transparency(&self) -> Result<Option<Transparency>> {
+        let Some(chunk) = self.chunk_by_type("tRNS") else {
+            return Ok(None);
+        };
+        Transparency::parse(&ChunkRef::from(chunk), &self.ihdr()?).map(Some)
+    }
+
+    pub fn set_transparency(&mut self, transparency: &Transparency) -> Result<()> {
+        transparency.validate(&self.ihdr()?, self.palette()?.as_ref())?;
+        self.set_chunk(transparency.to_chunk());
+        Ok(())
+    }
+
+    /// Splits an animated PNG into its frames.
+    pub fn frames(&self) -> Result<Vec<Frame>> {
+        apng::frames(&self.chunks)
+    }
+
+    pub fn trailer(&self) -> &[u8] {
+        &self.trailer
+    }
+
+    /// Replaces the data stored after IEND, returning the previous trailer.
+    pub fn set_trailer(&mut self, trailer: Vec<u8>) -> Vec<u8> {
+        std::mem::replace(&mut self.trailer, trailer)
+    }
+
+    /// Byte offset of the trailer from the start of the file.
+    pub fn trailer_offset(&self) -> usize {
+        self.header().len()
+            + self
+                .chunks
+                .iter()
+                .map(|chunk| chunk.length() as usize + 12)
+                .sum::<usize>()
+    }
+
+    pub fn image_modified(&self) -> bool {
+        self.image_modified
+    }
+
+    /// Keeps unknown unsafe-to-copy chunks when writing a modified image,
+    /// for callers that know the chunks are still valid.
+    pub fn set_keep_unsafe_to_copy(&mut self, keep: bool) {
+        self.keep_unsafe_to_copy = keep;
+    }
+
+    /// Uses `registry` to place chunks and to tell known chunks from
+    /// unknown ones, so registered private chunks survive image edits.
+    pub fn set_registry(&mut self, registry: Registry) {
+        self.registry = Some(registry);
+    }
+
+    /// The registry set with [`Png::set_registry`], or the standard one.
+    pub fn registry(&self) -> &Registry {
+        self.registry
+            .as_ref()
+            .unwrap_or_else(|| registry::standard())
+    }
+
+    /// Chunks that [`Png::as_bytes`] leaves out. Once the image has been
+    /// modified, the specification requires dropping ancillary chunks that
+    /// are unknown and not safe to copy, as they may depend on the old
+    /// image data.
+    pub fn discarded_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.iter().filter(|chunk| self.is_discarded(chunk))
+    }
+
+    /// The chunks that [`Png::as_bytes`] writes, in order.
+    pub(crate) fn kept_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.iter().filter(|chunk| !self.is_discarded(chunk))
+    }
+
+    fn is_discarded(&self, chunk: &Chunk) -> bool {
+        let chunk_type = chunk.chunk_type();
+        self.image_modified
+            && !self.keep_unsafe_to_copy
+            && !chunk_type.is_critical()
+            && !chunk_type.is_safe_to_copy()
+            && !self.registry().contains(chunk_type)
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
-        Self::STANDARD_HEADER
+        self.header()
             .iter()
             .cloned()
-            .chain(self.chunks.iter().flat_map(|chunk| chunk.as_bytes()))
+            .chain(self.kept_chunks().flat_map(|chunk| chunk.as_bytes()))
+            .chain(self.trailer.iter().cloned())
             .collect()
     }
 }
 
-impl Display for Png {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for chunk in self.chunks() {
-            writeln!(f, "{}", chunk)?;
-        }
-
-        Ok(())
+/// Lists the chunks and the trailer, for both `Png` and `PngRef`.
+fn write_listing<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    chunks: impl Iterator<Item = ChunkRef<'a>>,
+    trailer: &[u8],
+    trailer_offset: usize,
+) -> std::fmt::Result {
+    for chunk in chunks {
+        writeln!(f, "{}", chunk)?;
+    }
+    if !trailer.is_empty() {
+        writeln!(f, "Trailer {{",)?;
+        writeln!(f, "  Offset: {}", trailer_offset)?;
+        writeln!(f, "  Data: {} bytes", trailer.len())?;
+        writeln!(f, "}}",)?;
     }
+
+    Ok(())
 }
 
-mod util {
-    use super::Result;
-    pub fn calc_length(chunk: &[u8]) -> Result<u32> {
-        // first 4 bytes contain the length of the DATA segment of the PNG chunk
-        let length_slice: [u8; 4] = chunk[..4].try_into()?;
-        // data length + 4 bytes length + 4 bytes chunk type + 4 bytes crc
-        let length = u32::from_be_bytes(length_slice) + 4 * 3;
-        Ok(length)
+impl Display for Png {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_listing(
+            f,
+            self.chunks.iter().map(ChunkRef::from),
+            &self.trailer,
+            self.trailer_offset(),
+        )
     }
 }
 
 impl TryFrom<&[u8]> for Png {
     type Error = Error;
     fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
-        // NOTE: Improve error handaling
-        let mut png = Self { chunks: Vec::new() };
+        Png::parse(value, false)
+    }
+}
 
-        // first 8 bytes contain the PNG header
-        let (header, chunks) = value.split_at(8);
+/// An index of the chunks in a PNG file that borrows from the file
+/// contents, for listing and verifying chunks without copying them.
+#[derive(Debug, Clone)]
+pub struct PngRef<'a> {
+    bytes: &'a [u8],
+    /// Offset of each chunk from the start of the file.
+    offsets: Vec<usize>,
+    trailer_offset: usize,
+}
 
-        if header != Png::STANDARD_HEADER {
+impl<'a> PngRef<'a> {
+    /// Like `PngRef::try_from`, but accepts chunks with invalid crcs and
+    /// chunk types.
+    pub fn try_from_lenient(value: &'a [u8]) -> Result<PngRef<'a>> {
+        PngRef::parse(value, true)
+    }
+
+    fn parse(value: &'a [u8], lenient: bool) -> Result<PngRef<'a>> {
+        // first 8 bytes contain the PNG header
+        if value.len() < 8 {
+            return Err(Error::from("file is too short to be a png"));
+        }
+        if value[..8] != Png::STANDARD_HEADER {
             return Err(Error::from("invalid chunk headers"));
         }
 
-        let length = util::calc_length(chunks)?;
-        let (mut first, mut other) = chunks.split_at(length.try_into().unwrap());
+        let mut offsets = Vec::new();
+        let mut offset = 8;
         loop {
-            let chunk = Chunk::try_from(first)?;
-            png.chunks.push(chunk);
-            if other.is_empty() {
+            let chunk = ChunkRef::read(&value[offset..])?;
+            if !lenient {
+                chunk.verify()?;
+            }
+            offsets.push(offset);
+            offset += chunk.size();
+            if chunk.chunk_type() == &ChunkType::IEND || offset == value.len() {
                 break;
             }
-            let length = util::calc_length(other)?;
-            (first, other) = other.split_at(length.try_into().unwrap());
         }
 
-        Ok(png)
+        Ok(PngRef {
+            bytes: value,
+            offsets,
+            trailer_offset: offset,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    pub fn chunk(&self, index: usize) -> Option<ChunkRef<'a>> {
+        let offset = *self.offsets.get(index)?;
+        Some(self.chunk_at(offset))
+    }
+
+    fn chunk_at(&self, offset: usize) -> ChunkRef<'a> {
+        // every offset was read successfully while parsing
+        ChunkRef::read(&self.bytes[offset..]).unwrap()
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkRef<'a>> + '_ {
+        self.offsets.iter().map(|&offset| self.chunk_at(offset))
+    }
+
+    pub fn chunks_with_offsets(&self) -> impl Iterator<Item = (usize, ChunkRef<'a>)> + '_ {
+        self.offsets
+            .iter()
+            .map(|&offset| (offset, self.chunk_at(offset)))
+    }
+
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<ChunkRef<'a>> {
+        self.chunks()
+            .find(|chunk| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+    }
+
+    pub fn nth_chunk_by_type(&self, chunk_type: &str, nth: usize) -> Option<(usize, ChunkRef<'a>)> {
+        self.chunks_with_offsets()
+            .filter(|(_, chunk)| chunk.chunk_type().bytes() == chunk_type.as_bytes())
+            .nth(nth)
+    }
+
+    pub fn trailer(&self) -> &'a [u8] {
+        &self.bytes[self.trailer_offset..]
+    }
+
+    pub fn trailer_offset(&self) -> usize {
+        self.trailer_offset
+    }
+
+    /// Copies every chunk into a `Png` that can be edited.
+    pub fn to_owned(&self) -> Png {
+        let mut png = Png::from_chunks(self.chunks().map(|chunk| chunk.to_owned()).collect());
+        png.trailer = self.trailer().to_vec();
+        png
+    }
+}
+
+impl Display for PngRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_listing(f, self.chunks(), self.trailer(), self.trailer_offset())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PngRef<'a> {
+    type Error = Error;
+    fn try_from(value: &'a [u8]) -> std::result::Result<Self, Self::Error> {
+        PngRef::parse(value, false)
     }
 }
 
@@ -111,17 +446,18 @@ mod tests {
     use super::*;
     use crate::chunk::Chunk;
     use crate::chunk_type::ChunkType;
+    use crate::chunks::ihdr::ColorType;
+    use crate::registry::ChunkInfo;
+    use proptest::prelude::*;
     use std::convert::TryFrom;
     use std::str::FromStr;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> Png {
@@ -204,6 +540,75 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_truncated_chunk() {
+        let bytes = Png::try_from(&PNG_FILE[..]).unwrap().as_bytes();
+        assert!(Png::try_from(&bytes[..bytes.len() - 5]).is_err());
+        assert!(Png::try_from(&bytes[..10]).is_err());
+        assert!(Png::try_from(&bytes[..4]).is_err());
+    }
+
+    #[test]
+    fn test_lenient_from_bytes() {
+        let mut bytes = PNG_FILE.to_vec();
+        // flip a byte of the IHDR crc
+        bytes[32] ^= 1;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let png = Png::try_from_lenient(bytes.as_ref()).unwrap();
+        assert!(!png.chunks()[0].has_valid_crc());
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_trailer() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"hidden after IEND");
+
+        let mut png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailer(), b"hidden after IEND");
+        assert_eq!(png.trailer_offset(), PNG_FILE.len());
+        assert_eq!(png.as_bytes(), bytes);
+
+        let old = png.set_trailer(Vec::new());
+        assert_eq!(old, b"hidden after IEND");
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_png_ref() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(b"hidden after IEND");
+
+        let png_ref = PngRef::try_from(bytes.as_slice()).unwrap();
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(png_ref.len(), 7);
+        let offsets: Vec<usize> = png
+            .chunks_with_offsets()
+            .map(|(offset, _)| offset)
+            .collect();
+        assert_eq!(png_ref.offsets(), offsets);
+        assert!(png_ref.chunks().all(|chunk| chunk.has_valid_crc()));
+        assert_eq!(png_ref.trailer(), b"hidden after IEND");
+        assert_eq!(png_ref.trailer_offset(), PNG_FILE.len());
+
+        let (offset, chunk) = png_ref.nth_chunk_by_type("IDAT", 0).unwrap();
+        assert_eq!(
+            &bytes[offset + 8..offset + 8 + chunk.data().len()],
+            chunk.data()
+        );
+        assert_eq!(png_ref.chunk(0).unwrap().chunk_type(), &ChunkType::IHDR);
+        assert!(png_ref.chunk_by_type("tEXt").is_none());
+        assert_eq!(png_ref.to_owned().as_bytes(), bytes);
+        assert_eq!(png_ref.to_string(), png.to_string());
+
+        bytes[32] ^= 1;
+        assert!(PngRef::try_from(bytes.as_slice()).is_err());
+        let png_ref = PngRef::try_from_lenient(bytes.as_slice()).unwrap();
+        assert!(!png_ref.chunk(0).unwrap().has_valid_crc());
+    }
+
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
@@ -228,6 +633,19 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_append_chunk_keeps_iend_last() {
+        let mut png = Png::from_chunks(Vec::new());
+        png.append_chunk(Chunk::new(ChunkType::IEND, Vec::new()));
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["TeSt", "IEND"]);
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -237,6 +655,144 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "TeSt");
+        assert!(png
+            .insert_chunk(10, chunk_from_strings("TeSt", "Message").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_nth_chunk_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am the second FrSt").unwrap());
+
+        let (offset, chunk) = png.nth_chunk_by_type("FrSt", 1).unwrap();
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the second FrSt");
+        assert_eq!(offset, 8 + (12 + 20) + (12 + 18) + (12 + 19));
+        assert!(png.nth_chunk_by_type("FrSt", 2).is_none());
+    }
+
+    #[test]
+    fn test_set_chunk() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        let time = Chunk::new(ChunkType::TIME, vec![0x07, 0xe7, 1, 1, 0, 0, 0]);
+        assert!(png.set_chunk(time).is_none());
+        let chrm = Chunk::new(ChunkType::CHRM, vec![0; 32]);
+        assert!(png.set_chunk(chrm).is_none());
+        let gama = Chunk::new(ChunkType::GAMA, 100000u32.to_be_bytes().to_vec());
+        let replaced = png.set_chunk(gama).unwrap();
+        assert_eq!(replaced.data(), 45455u32.to_be_bytes());
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            ["IHDR", "sRGB", "gAMA", "pHYs", "cHRM", "IDAT", "RuSt", "tIME", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_remove_chunks() {
+        let mut png = testing_png();
+        let removed = png.remove_chunks(|chunk| chunk.chunk_type().is_critical());
+        assert_eq!(removed.len(), 2);
+        assert!(png
+            .chunks()
+            .iter()
+            .all(|chunk| !chunk.chunk_type().is_critical()));
+    }
+
+    #[test]
+    fn test_discard_unsafe_to_copy() {
+        let unsafe_chunk = chunk_from_strings("unSF", "depends on IDAT").unwrap();
+        let safe_chunk = chunk_from_strings("saFe", "does not").unwrap();
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let unsafe_length = unsafe_chunk.as_bytes().len();
+        png.append_chunk(unsafe_chunk);
+        png.append_chunk(safe_chunk);
+        png.append_chunk(Chunk::new(ChunkType::GAMA, vec![0, 0, 0, 1]));
+        assert!(!png.image_modified());
+        assert_eq!(png.discarded_chunks().count(), 0);
+
+        let with_unsafe = png.as_bytes();
+        png.append_chunk(Chunk::new(ChunkType::IDAT, Vec::new()));
+        assert!(png.image_modified());
+        let discarded: Vec<String> = png
+            .discarded_chunks()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(discarded, ["unSF"]);
+
+        let bytes = png.as_bytes();
+        let written = Png::try_from(bytes.as_slice()).unwrap();
+        assert!(written.chunk_by_type("unSF").is_none());
+        assert!(written.chunk_by_type("saFe").is_some());
+        assert_eq!(bytes.len(), with_unsafe.len() + 12 - unsafe_length);
+
+        png.set_keep_unsafe_to_copy(true);
+        assert!(Png::try_from(png.as_bytes().as_slice())
+            .unwrap()
+            .chunk_by_type("unSF")
+            .is_some());
+    }
+
+    #[test]
+    fn test_registered_chunk_not_discarded() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("unSF", "depends on IDAT").unwrap());
+        png.append_chunk(Chunk::new(ChunkType::IDAT, Vec::new()));
+        assert_eq!(png.discarded_chunks().count(), 1);
+
+        let mut registry = Registry::default();
+        registry
+            .register(ChunkInfo::new(ChunkType::from_str("unSF").unwrap(), "Mine"))
+            .unwrap();
+        png.set_registry(registry);
+        assert_eq!(png.discarded_chunks().count(), 0);
+    }
+
+    #[test]
+    fn test_set_palette() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.palette().unwrap().is_none());
+
+        let palette = Palette::new(vec![[1, 2, 3], [4, 5, 6]]).unwrap();
+        png.set_palette(&palette).unwrap();
+        assert_eq!(png.palette().unwrap(), Some(palette));
+        assert_eq!(&png.chunks()[4].chunk_type().to_string(), "PLTE");
+
+        // the dice image is truecolour with alpha, which forbids tRNS
+        assert!(png.set_transparency(&Transparency::Rgb(0, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn test_set_palette_checks_transparency() {
+        let palette = Palette::new(vec![[0, 0, 0], [1, 1, 1], [2, 2, 2]]).unwrap();
+        let mut png = Png::from_chunks(vec![
+            Ihdr::new(1, 1, 8, ColorType::Indexed).to_chunk(),
+            palette.to_chunk(),
+            Transparency::Indexed(vec![0, 128, 255]).to_chunk(),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]);
+
+        let smaller = Palette::new(vec![[0, 0, 0], [1, 1, 1]]).unwrap();
+        assert!(png.set_palette(&smaller).is_err());
+        assert_eq!(png.palette().unwrap(), Some(palette));
+
+        let recoloured = Palette::new(vec![[9, 9, 9]; 3]).unwrap();
+        png.set_palette(&recoloured).unwrap();
+        assert_eq!(png.palette().unwrap(), Some(recoloured));
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -247,7 +803,7 @@ mod tests {
     fn test_as_bytes() {
         let png = Png::try_from(&PNG_FILE[..]).unwrap();
         let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.iter().copied().collect();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
         assert_eq!(actual, expected);
     }
 
@@ -515,4 +1071,91 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
+
+    fn arb_chunk() -> impl Strategy<Value = Chunk> {
+        let chunk_type = "[a-zA-Z]{2}[A-Z][a-zA-Z]"
+            .prop_map(|name| ChunkType::from_str(&name).unwrap())
+            .prop_filter("IEND ends the file", |chunk_type| {
+                chunk_type != &ChunkType::IEND
+            });
+        (chunk_type, prop::collection::vec(any::<u8>(), 0..256))
+            .prop_map(|(chunk_type, data)| Chunk::new(chunk_type, data))
+    }
+
+    fn arb_png() -> impl Strategy<Value = Png> {
+        (
+            prop::collection::vec(arb_chunk(), 0..16),
+            prop::collection::vec(any::<u8>(), 0..32),
+        )
+            .prop_map(|(mut chunks, trailer)| {
+                chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+                let mut png = Png::from_chunks(chunks);
+                png.set_trailer(trailer);
+                png
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_png_round_trip(png in arb_png()) {
+            let bytes = png.as_bytes();
+            let parsed = Png::try_from(bytes.as_slice()).unwrap();
+            prop_assert_eq!(parsed.chunks(), png.chunks());
+            prop_assert_eq!(parsed.trailer(), png.trailer());
+            prop_assert_eq!(parsed.as_bytes(), bytes.clone());
+
+            let png_ref = PngRef::try_from(bytes.as_slice()).unwrap();
+            prop_assert_eq!(png_ref.to_owned().as_bytes(), bytes);
+        }
+
+        #[test]
+        fn prop_encode_decode(
+            png in arb_png(),
+            message in prop::collection::vec(any::<u8>(), 0..512),
+        ) {
+            prop_assume!(png.chunk_by_type("ruSt").is_none());
+            let bytes = png.as_bytes();
+
+            // a message stored in a new chunk is found again by type once the
+            // file is written and parsed back, and IEND stays last
+            let mut encoded = Png::try_from(bytes.as_slice()).unwrap();
+            encoded.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), message.clone()));
+            let written = encoded.as_bytes();
+            let decoded = PngRef::try_from(written.as_slice()).unwrap();
+            prop_assert_eq!(decoded.chunk_by_type("ruSt").unwrap().data(), message.as_slice());
+            let last = decoded.chunks().last().unwrap();
+            prop_assert_eq!(last.chunk_type(), &ChunkType::IEND);
+
+            // a message stored after IEND comes back as the trailer
+            let mut encoded = Png::try_from(bytes.as_slice()).unwrap();
+            encoded.set_trailer(message.clone());
+            let written = encoded.as_bytes();
+            let decoded = PngRef::try_from(written.as_slice()).unwrap();
+            prop_assert_eq!(decoded.trailer(), message.as_slice());
+        }
+
+        #[test]
+        fn prop_parser_never_panics(
+            png in arb_png(),
+            mutations in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..8),
+            truncate in any::<prop::sample::Index>(),
+            garbage in prop::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let mut bytes = png.as_bytes();
+            for (index, byte) in mutations {
+                let index = index.index(bytes.len());
+                bytes[index] = byte;
+            }
+            bytes.truncate(truncate.index(bytes.len() + 1));
+            bytes.extend_from_slice(&garbage);
+
+            let _ = Png::try_from(bytes.as_slice());
+            let _ = Png::try_from_lenient(bytes.as_slice());
+            if let Ok(png) = PngRef::try_from_lenient(bytes.as_slice()) {
+                let _ = png.to_string();
+            }
+            let _ = crate::check::check(&bytes);
+            let _ = crate::repair::repair(&bytes);
+        }
+    }
 }
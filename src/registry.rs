@@ -0,0 +1,373 @@
+//! Metadata about chunk types: what they are called, where the
+//! specification defines them, how they may be ordered and how to decode
+//! them.
+//!
+//! [`standard`] knows every chunk type registered in the specification.
+//! Applications with their own private chunks can start from
+//! [`Registry::default`], [`Registry::register`] them and hand the registry
+//! to [`crate::check::check_with`], [`Registry::describe`] or
+//! [`crate::png::Png::set_registry`].
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::chunk::ChunkRef;
+use crate::chunk_type::ChunkType;
+use crate::chunks::apng::{AnimationControl, FrameControl, FrameData};
+use crate::chunks::color::{
+    Chromaticities, Cicp, ContentLightLevel, Gamma, IccProfile, MasteringDisplay, RenderingIntent,
+};
+use crate::chunks::exif::Exif;
+use crate::chunks::ihdr::Ihdr;
+use crate::chunks::palette::Palette;
+use crate::chunks::phys::Phys;
+use crate::chunks::text::Text;
+use crate::chunks::time::Time;
+use crate::{Error, Result};
+
+/// Decodes the contents of a chunk into a human readable form.
+pub type Decoder = fn(&ChunkRef) -> Result<String>;
+/// Checks the contents of a chunk, independently of the rest of the file.
+pub type Validator = fn(&ChunkRef) -> Result<()>;
+
+/// Where a chunk may appear relative to the critical chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Anywhere between IHDR and IEND.
+    Anywhere,
+    /// Before PLTE and IDAT.
+    BeforePlte,
+    /// After PLTE (if any) and before IDAT.
+    AfterPlte,
+    /// Before IDAT.
+    BeforeIdat,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkInfo {
+    pub chunk_type: ChunkType,
+    /// Human readable name, e.g. "Image header".
+    pub name: &'static str,
+    /// Section of the specification that defines the chunk, if any.
+    pub section: Option<&'static str>,
+    /// Whether a file may hold more than one chunk of this type.
+    pub multiple: bool,
+    pub placement: Placement,
+    pub decoder: Option<Decoder>,
+    pub validator: Option<Validator>,
+}
+
+impl ChunkInfo {
+    /// Metadata for a chunk type that may appear any number of times,
+    /// anywhere in the file.
+    pub fn new(chunk_type: ChunkType, name: &'static str) -> ChunkInfo {
+        ChunkInfo {
+            chunk_type,
+            name,
+            section: None,
+            multiple: true,
+            placement: Placement::Anywhere,
+            decoder: None,
+            validator: None,
+        }
+    }
+
+    pub fn single(mut self) -> ChunkInfo {
+        self.multiple = false;
+        self
+    }
+
+    pub fn placement(mut self, placement: Placement) -> ChunkInfo {
+        self.placement = placement;
+        self
+    }
+
+    pub fn decoder(mut self, decoder: Decoder) -> ChunkInfo {
+        self.decoder = Some(decoder);
+        self
+    }
+
+    pub fn validator(mut self, validator: Validator) -> ChunkInfo {
+        self.validator = Some(validator);
+        self
+    }
+
+    fn section(mut self, section: &'static str) -> ChunkInfo {
+        self.section = Some(section);
+        self
+    }
+
+    /// Runs the validator, or the decoder if there is no validator.
+    pub fn validate(&self, chunk: &ChunkRef) -> Result<()> {
+        match (self.validator, self.decoder) {
+            (Some(validator), _) => validator(chunk),
+            (None, Some(decoder)) => decoder(chunk).map(|_| ()),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    entries: HashMap<ChunkType, ChunkInfo>,
+}
+
+impl Registry {
+    /// A registry that knows no chunk types at all.
+    pub fn empty() -> Registry {
+        Registry {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Adds metadata for a private chunk type, replacing any earlier
+    /// registration of the same type.
+    pub fn register(&mut self, info: ChunkInfo) -> Result<Option<ChunkInfo>> {
+        if let Err(error) = info.chunk_type.validate() {
+            return Err(Error::from(format!("{}: {error}", info.chunk_type)));
+        }
+        if info.chunk_type.is_public() {
+            return Err(Error::from(format!(
+                "{} is a public chunk type, only private ones can be registered",
+                info.chunk_type
+            )));
+        }
+        Ok(self.entries.insert(info.chunk_type, info))
+    }
+
+    pub fn get(&self, chunk_type: &ChunkType) -> Option<&ChunkInfo> {
+        self.entries.get(chunk_type)
+    }
+
+    pub fn contains(&self, chunk_type: &ChunkType) -> bool {
+        self.entries.contains_key(chunk_type)
+    }
+
+    pub fn placement(&self, chunk_type: &ChunkType) -> Placement {
+        self.get(chunk_type)
+            .map_or(Placement::Anywhere, |info| info.placement)
+    }
+
+    /// Decodes a chunk with its registered decoder.
+    ///
+    /// Returns `None` for chunk types without a decoder, and an error when
+    /// the chunk is malformed.
+    pub fn describe(&self, chunk: &ChunkRef) -> Option<Result<String>> {
+        let decoder = self.get(chunk.chunk_type())?.decoder?;
+        Some(decoder(chunk))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ChunkInfo> {
+        self.entries.values()
+    }
+
+    fn add(&mut self, info: ChunkInfo) {
+        self.entries.insert(info.chunk_type, info);
+    }
+}
+
+impl Default for Registry {
+    /// Every chunk type registered in the specification.
+    fn default() -> Registry {
+        use Placement::*;
+
+        let chunk = |chunk_type, name, section| ChunkInfo::new(chunk_type, name).section(section);
+
+        let mut registry = Registry::empty();
+        for info in [
+            chunk(ChunkType::IHDR, "Image header", "11.2.2")
+                .single()
+                .decoder(|chunk| Ihdr::try_from(chunk).map(|ihdr| ihdr.to_string())),
+            chunk(ChunkType::PLTE, "Palette", "11.2.3")
+                .single()
+                .placement(BeforeIdat)
+                .decoder(|chunk| Palette::try_from(chunk).map(|palette| palette.to_string())),
+            chunk(ChunkType::IDAT, "Image data", "11.2.4")
+                .decoder(|chunk| Ok(format!("{} bytes of compressed image data", chunk.length()))),
+            chunk(ChunkType::IEND, "Image trailer", "11.2.5")
+                .single()
+                .decoder(|_| Ok(String::from("End of image"))),
+            chunk(ChunkType::TRNS, "Transparency", "11.3.1.1")
+                .single()
+                .placement(AfterPlte),
+            chunk(
+                ChunkType::CHRM,
+                "Primary chromaticities and white point",
+                "11.3.2.1",
+            )
+            .single()
+            .placement(BeforePlte)
+            .decoder(|chunk| Chromaticities::try_from(chunk).map(|chrm| chrm.to_string())),
+            chunk(ChunkType::GAMA, "Image gamma", "11.3.2.2")
+                .single()
+                .placement(BeforePlte)
+                .decoder(|chunk| Gamma::try_from(chunk).map(|gamma| gamma.to_string())),
+            chunk(ChunkType::ICCP, "Embedded ICC profile", "11.3.2.3")
+                .single()
+                .placement(BeforePlte)
+                .decoder(|chunk| IccProfile::try_from(chunk).map(|profile| profile.to_string())),
+            chunk(ChunkType::SBIT, "Significant bits", "11.3.2.4")
+                .single()
+                .placement(BeforePlte),
+            chunk(ChunkType::SRGB, "Standard RGB colour space", "11.3.2.5")
+                .single()
+                .placement(BeforePlte)
+                .decoder(|chunk| RenderingIntent::try_from(chunk).map(|intent| intent.to_string())),
+            chunk(
+                ChunkType::CICP,
+                "Coding-independent code points",
+                "11.3.2.6",
+            )
+            .single()
+            .placement(BeforePlte)
+            .decoder(|chunk| Cicp::try_from(chunk).map(|cicp| cicp.to_string())),
+            chunk(
+                ChunkType::MDCV,
+                "Mastering display colour volume",
+                "11.3.2.7",
+            )
+            .single()
+            .placement(BeforePlte)
+            .decoder(|chunk| MasteringDisplay::try_from(chunk).map(|mdcv| mdcv.to_string())),
+            chunk(
+                ChunkType::CLLI,
+                "Content light level information",
+                "11.3.2.8",
+            )
+            .single()
+            .placement(BeforePlte)
+            .decoder(|chunk| ContentLightLevel::try_from(chunk).map(|clli| clli.to_string())),
+            chunk(ChunkType::TEXT, "Textual data", "11.3.3.2")
+                .decoder(|chunk| Text::try_from(chunk).map(|text| text.to_string())),
+            chunk(ChunkType::ZTXT, "Compressed textual data", "11.3.3.3")
+                .decoder(|chunk| Text::try_from(chunk).map(|text| text.to_string())),
+            chunk(ChunkType::ITXT, "International textual data", "11.3.3.4")
+                .decoder(|chunk| Text::try_from(chunk).map(|text| text.to_string())),
+            chunk(ChunkType::BKGD, "Background colour", "11.3.4.1")
+                .single()
+                .placement(AfterPlte),
+            chunk(ChunkType::HIST, "Image histogram", "11.3.4.2")
+                .single()
+                .placement(AfterPlte),
+            chunk(ChunkType::PHYS, "Physical pixel dimensions", "11.3.4.3")
+                .single()
+                .placement(BeforeIdat)
+                .decoder(|chunk| Phys::try_from(chunk).map(|phys| phys.to_string())),
+            chunk(ChunkType::SPLT, "Suggested palette", "11.3.4.4").placement(BeforeIdat),
+            chunk(
+                ChunkType::EXIF,
+                "Exchangeable image file profile",
+                "11.3.4.5",
+            )
+            .single()
+            .placement(BeforeIdat)
+            .decoder(|chunk| Exif::try_from(chunk).map(|exif| exif.to_string())),
+            chunk(ChunkType::TIME, "Image last-modification time", "11.3.5.1")
+                .single()
+                .decoder(|chunk| Time::try_from(chunk).map(|time| time.to_string())),
+            chunk(ChunkType::ACTL, "Animation control", "11.3.6.1")
+                .single()
+                .placement(BeforeIdat)
+                .decoder(|chunk| AnimationControl::try_from(chunk).map(|actl| actl.to_string())),
+            chunk(ChunkType::FCTL, "Frame control", "11.3.6.2")
+                .decoder(|chunk| FrameControl::try_from(chunk).map(|fctl| fctl.to_string())),
+            chunk(ChunkType::FDAT, "Frame data", "11.3.6.3").decoder(|chunk| {
+                FrameData::try_from(chunk).map(|fdat| {
+                    format!(
+                        "Sequence number: {}\n{} bytes of compressed frame data",
+                        fdat.sequence_number,
+                        fdat.data.len()
+                    )
+                })
+            }),
+        ] {
+            registry.add(info);
+        }
+
+        // registered extensions, defined outside the specification itself
+        for info in [
+            ChunkInfo::new(ChunkType::OFFS, "Image offset"),
+            ChunkInfo::new(ChunkType::PCAL, "Calibration of pixel values"),
+            ChunkInfo::new(ChunkType::SCAL, "Physical scale of image subject"),
+        ] {
+            registry.add(info.single().placement(BeforeIdat));
+        }
+
+        registry
+    }
+}
+
+/// The registry of chunk types defined by the specification.
+pub fn standard() -> &'static Registry {
+    static STANDARD: OnceLock<Registry> = OnceLock::new();
+    STANDARD.get_or_init(Registry::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_standard_chunks() {
+        let registry = standard();
+        let info = registry.get(&ChunkType::PHYS).unwrap();
+        assert_eq!(info.name, "Physical pixel dimensions");
+        assert_eq!(info.section, Some("11.3.4.3"));
+        assert!(!info.multiple);
+        assert_eq!(info.placement, Placement::BeforeIdat);
+
+        assert!(registry.get(&ChunkType::TEXT).unwrap().multiple);
+        assert_eq!(registry.placement(&ChunkType::GAMA), Placement::BeforePlte);
+        assert_eq!(registry.placement(&ChunkType::EXIF), Placement::BeforeIdat);
+        assert_eq!(
+            registry.placement(&ChunkType::from_str("ruSt").unwrap()),
+            Placement::Anywhere
+        );
+        assert!(registry.iter().all(|info| info.chunk_type.is_valid()));
+        assert_eq!(registry.iter().count(), 28);
+
+        let chunk = Chunk::new(ChunkType::GAMA, 45455u32.to_be_bytes().to_vec());
+        assert!(registry.describe(&ChunkRef::from(&chunk)).unwrap().is_ok());
+        let chunk = Chunk::new(ChunkType::TRNS, vec![0]);
+        assert!(registry.describe(&ChunkRef::from(&chunk)).is_none());
+    }
+
+    #[test]
+    fn test_register_private_chunk() {
+        let mut registry = Registry::default();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let info = ChunkInfo::new(chunk_type, "Rust level")
+            .single()
+            .placement(Placement::BeforeIdat)
+            .decoder(|chunk| Ok(format!("Level {}", chunk.data()[0])))
+            .validator(|chunk| match chunk.data() {
+                [level] if *level <= 9 => Ok(()),
+                _ => Err(Error::from("expected a single level from 0 to 9")),
+            });
+        assert!(registry.register(info).unwrap().is_none());
+        assert!(registry.register(info).unwrap().is_some());
+
+        let chunk = Chunk::new(chunk_type, vec![3]);
+        assert_eq!(
+            registry.describe(&ChunkRef::from(&chunk)).unwrap().unwrap(),
+            "Level 3"
+        );
+        let info = registry.get(&chunk_type).unwrap();
+        assert!(info.validate(&ChunkRef::from(&chunk)).is_ok());
+        assert!(info
+            .validate(&ChunkRef::from(&Chunk::new(chunk_type, vec![10])))
+            .is_err());
+
+        assert!(registry
+            .register(ChunkInfo::new(ChunkType::GAMA, "Gamma"))
+            .is_err());
+        assert!(registry
+            .register(ChunkInfo::new(ChunkType::from_str("ruSt").unwrap(), "x"))
+            .is_ok());
+        assert!(registry
+            .register(ChunkInfo::new(ChunkType::from_str("rust").unwrap(), "x"))
+            .is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Recovery of damaged files: wrong CRCs, broken chunk lengths, garbage
+//! after IEND and a missing IEND.
+
+use std::fmt::Display;
+
+use crate::checksum::Crc;
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::{Error, Result};
+
+/// A single change made while repairing a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// Offset in the original file the fix applies to.
+    pub offset: usize,
+    pub description: String,
+    /// Whether the fix alters a critical chunk, which needs `force` in
+    /// [`repair_with`].
+    pub critical: bool,
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "offset {} ({:#x}): {}",
+            self.offset, self.offset, self.description
+        )?;
+        if self.critical {
+            write!(f, " [critical]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of [`repair`].
+pub struct Repaired {
+    pub png: Png,
+    /// The fixes applied to `png`.
+    pub fixes: Vec<Fix>,
+    /// Critical fixes left out because `force` was not given.
+    pub skipped: Vec<Fix>,
+}
+
+impl Repaired {
+    /// Fixes that alter critical chunks.
+    pub fn critical_fixes(&self) -> impl Iterator<Item = &Fix> {
+        self.fixes.iter().filter(|fix| fix.critical)
+    }
+}
+
+fn is_chunk_type(bytes: &[u8]) -> bool {
+    bytes.len() == 4 && bytes.iter().all(u8::is_ascii_alphabetic)
+}
+
+/// Whether a chunk could start at `offset`, or the file ends exactly there.
+fn is_boundary(bytes: &[u8], offset: usize) -> bool {
+    offset == bytes.len() || bytes.get(offset + 4..offset + 8).is_some_and(is_chunk_type)
+}
+
+/// Looks for the real end of the chunk at `offset` by finding a data length
+/// whose CRC matches the four bytes that follow and that lands on a chunk
+/// boundary. The crc is computed in a single pass over the candidates.
+fn recover_length(bytes: &[u8], offset: usize, chunk_type: ChunkType) -> Option<usize> {
+    let data_start = offset + 8;
+    let mut crc = Crc::for_chunk(&chunk_type);
+    let mut fed = data_start;
+    (data_start..=bytes.len().saturating_sub(4))
+        .filter(|&crc_start| is_boundary(bytes, crc_start + 4))
+        .find(|&crc_start| {
+            crc.update(&bytes[fed..crc_start]);
+            fed = crc_start;
+            let stored = u32::from_be_bytes(bytes[crc_start..crc_start + 4].try_into().unwrap());
+            crc.value() == stored
+        })
+        .map(|crc_start| crc_start - data_start)
+}
+
+/// Parses a damaged file, fixing whatever can be recovered.
+///
+/// Every change is listed in [`Repaired::fixes`]; callers decide whether to
+/// accept the ones that touch critical chunks.
+pub fn repair(bytes: &[u8]) -> Result<Repaired> {
+    repair_with(bytes, true)
+}
+
+/// Like [`repair`], but without `force` the fixes that alter critical
+/// chunks are only listed in [`Repaired::skipped`]. A skipped crc fix keeps
+/// the stored crc; a skipped length fix keeps the rest of the file as it
+/// is, after the chunks read so far.
+pub fn repair_with(bytes: &[u8], force: bool) -> Result<Repaired> {
+    if bytes.len() < 8 {
+        return Err(Error::from("file is too short to be a png"));
+    }
+    let mut fixes = Vec::new();
+    let mut skipped = Vec::new();
+    // records the fix and tells whether to apply it
+    let mut apply = |fix: Fix| {
+        if fix.critical && !force {
+            skipped.push(fix);
+            false
+        } else {
+            fixes.push(fix);
+            true
+        }
+    };
+
+    if bytes[..8] != Png::STANDARD_HEADER {
+        apply(Fix {
+            offset: 0,
+            description: String::from("replaced invalid PNG signature"),
+            critical: false,
+        });
+    }
+
+    let mut chunks = Vec::new();
+    // the unrepaired rest of the file, after a skipped fix
+    let mut kept = Vec::new();
+    let mut offset = 8;
+    let mut seen_iend = false;
+    while offset < bytes.len() && !seen_iend {
+        let rest = &bytes[offset..];
+        if rest.len() < 12 || !is_chunk_type(&rest[4..8]) {
+            if !apply(Fix {
+                offset,
+                description: format!("dropped {} unreadable bytes", rest.len()),
+                critical: true,
+            }) {
+                kept = rest.to_vec();
+            }
+            offset = bytes.len();
+            break;
+        }
+
+        let length = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let chunk_type = ChunkType::new_unchecked(rest[4..8].try_into().unwrap());
+        let critical = chunk_type.is_critical();
+        let fits = length + 12 <= rest.len();
+
+        let stored_crc =
+            fits.then(|| u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap()));
+        let chunk = fits.then(|| Chunk::new(chunk_type, rest[8..8 + length].to_vec()));
+        let stored = || Chunk::try_from_lenient(&rest[..length + 12]).unwrap();
+        let crc_matches = chunk.as_ref().map(|chunk| Some(chunk.crc()) == stored_crc);
+
+        let chunk = match (chunk, crc_matches) {
+            (Some(chunk), Some(true)) => chunk,
+            (Some(chunk), _) if is_boundary(bytes, offset + length + 12) => {
+                let fix = Fix {
+                    offset,
+                    description: format!(
+                        "recomputed {chunk_type} crc, stored {:#010x} but computed {:#010x}",
+                        stored_crc.unwrap(),
+                        chunk.crc()
+                    ),
+                    critical,
+                };
+                if apply(fix) {
+                    chunk
+                } else {
+                    stored()
+                }
+            }
+            (chunk, _) => match recover_length(bytes, offset, chunk_type) {
+                Some(recovered) => {
+                    if !apply(Fix {
+                        offset,
+                        description: format!(
+                            "fixed {chunk_type} length from {length} to {recovered}"
+                        ),
+                        critical,
+                    }) {
+                        kept = rest.to_vec();
+                        offset = bytes.len();
+                        break;
+                    }
+                    Chunk::new(chunk_type, rest[8..8 + recovered].to_vec())
+                }
+                None => match chunk {
+                    Some(chunk) => {
+                        let fix = Fix {
+                            offset,
+                            description: format!(
+                                "recomputed {chunk_type} crc, the chunk boundary could not be verified"
+                            ),
+                            critical,
+                        };
+                        if apply(fix) {
+                            chunk
+                        } else {
+                            stored()
+                        }
+                    }
+                    None => {
+                        if !apply(Fix {
+                            offset,
+                            description: format!(
+                                "dropped truncated {chunk_type} chunk ({} bytes)",
+                                rest.len()
+                            ),
+                            critical,
+                        }) {
+                            kept = rest.to_vec();
+                        }
+                        offset = bytes.len();
+                        break;
+                    }
+                },
+            },
+        };
+
+        offset += chunk.length() as usize + 12;
+        seen_iend = chunk.chunk_type() == &ChunkType::IEND;
+        chunks.push(chunk);
+    }
+
+    if offset < bytes.len() {
+        apply(Fix {
+            offset,
+            description: format!("dropped {} bytes after IEND", bytes.len() - offset),
+            critical: false,
+        });
+    }
+    // an IEND would go before the kept bytes, so it is only added to a
+    // fully repaired file
+    if !seen_iend && kept.is_empty() {
+        apply(Fix {
+            offset: bytes.len(),
+            description: String::from("appended missing IEND chunk"),
+            critical: false,
+        });
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+    }
+
+    let mut png = Png::from_chunks(chunks);
+    png.set_trailer(kept);
+    Ok(Repaired {
+        png,
+        fixes,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png() -> Vec<u8> {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"secret".to_vec()),
+            Chunk::new(ChunkType::IDAT, vec![1, 2, 3]),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ])
+        .as_bytes()
+    }
+
+    const RUST_OFFSET: usize = 8 + 25;
+
+    #[test]
+    fn test_intact_file() {
+        let bytes = testing_png();
+        let repaired = repair(&bytes).unwrap();
+        assert!(repaired.fixes.is_empty());
+        assert_eq!(repaired.png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_recompute_crc() {
+        let mut bytes = testing_png();
+        bytes[RUST_OFFSET + 12 + 6 - 1] ^= 0xff;
+
+        let repaired = repair(&bytes).unwrap();
+        assert_eq!(repaired.fixes.len(), 1);
+        assert!(repaired.fixes[0]
+            .description
+            .starts_with("recomputed ruSt crc"));
+        assert!(!repaired.fixes[0].critical);
+        assert_eq!(repaired.critical_fixes().count(), 0);
+        assert_eq!(repaired.png.as_bytes(), testing_png());
+    }
+
+    #[test]
+    fn test_critical_crc() {
+        let mut bytes = testing_png();
+        bytes[8 + 8] = 1;
+
+        let repaired = repair(&bytes).unwrap();
+        assert_eq!(repaired.critical_fixes().count(), 1);
+    }
+
+    #[test]
+    fn test_skip_critical_fixes() {
+        let mut corrupted = testing_png();
+        corrupted[8 + 8] = 1;
+        let mut bytes = corrupted.clone();
+        bytes.extend_from_slice(b"garbage");
+        bytes[RUST_OFFSET + 12 + 6 - 1] ^= 0xff;
+
+        let repaired = repair_with(&bytes, false).unwrap();
+        let descriptions: Vec<&str> = repaired
+            .fixes
+            .iter()
+            .map(|fix| fix.description.as_str())
+            .collect();
+        assert_eq!(descriptions.len(), 2);
+        assert!(descriptions[0].starts_with("recomputed ruSt crc"));
+        assert_eq!(descriptions[1], "dropped 7 bytes after IEND");
+        assert_eq!(repaired.skipped.len(), 1);
+        assert!(repaired.skipped[0].critical);
+        // the IHDR keeps its stored crc
+        assert_eq!(repaired.png.as_bytes(), corrupted);
+
+        // a broken IDAT length leaves the rest of the file alone
+        let mut bytes = testing_png();
+        bytes[RUST_OFFSET + 18 + 3] = 200;
+        let repaired = repair_with(&bytes, false).unwrap();
+        assert!(repaired.fixes.is_empty());
+        assert_eq!(
+            repaired.skipped[0].description,
+            "fixed IDAT length from 200 to 3"
+        );
+        assert_eq!(repaired.png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_fix_length() {
+        let mut bytes = testing_png();
+        bytes[RUST_OFFSET + 3] = 200;
+
+        let repaired = repair(&bytes).unwrap();
+        assert_eq!(repaired.fixes.len(), 1);
+        assert_eq!(
+            repaired.fixes[0].description,
+            "fixed ruSt length from 200 to 6"
+        );
+        assert_eq!(repaired.png.as_bytes(), testing_png());
+    }
+
+    #[test]
+    fn test_fix_length_of_large_chunk() {
+        // every offset inside the data looks like a chunk boundary
+        let data = vec![b'a'; 1 << 20];
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let original = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(chunk_type, data),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ])
+        .as_bytes();
+        let mut bytes = original.clone();
+        bytes[RUST_OFFSET..RUST_OFFSET + 4].copy_from_slice(&[0x7f, 0, 0, 0]);
+
+        let repaired = repair(&bytes).unwrap();
+        assert_eq!(
+            repaired.fixes[0].description,
+            format!("fixed ruSt length from {} to {}", 0x7f00_0000, 1 << 20)
+        );
+        assert_eq!(repaired.png.as_bytes(), original);
+    }
+
+    #[test]
+    fn test_trailing_data_and_missing_iend() {
+        let mut bytes = testing_png();
+        bytes.extend_from_slice(b"garbage");
+        let repaired = repair(&bytes).unwrap();
+        assert_eq!(repaired.fixes[0].description, "dropped 7 bytes after IEND");
+        assert_eq!(repaired.png.as_bytes(), testing_png());
+
+        let bytes = testing_png();
+        let repaired = repair(&bytes[..bytes.len() - 12]).unwrap();
+        assert_eq!(repaired.fixes[0].description, "appended missing IEND chunk");
+        assert_eq!(repaired.png.as_bytes(), testing_png());
+    }
+
+    #[test]
+    fn test_truncated_chunk() {
+        let bytes = testing_png();
+        let repaired = repair(&bytes[..bytes.len() - 14]).unwrap();
+        let descriptions: Vec<&str> = repaired
+            .fixes
+            .iter()
+            .map(|fix| fix.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "dropped truncated IDAT chunk (13 bytes)",
+                "appended missing IEND chunk"
+            ]
+        );
+        assert!(repaired.fixes[0].critical);
+    }
+}
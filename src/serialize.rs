@@ -0,0 +1,351 @@
+//! `Serialize` and `Deserialize` for [`Png`], [`Chunk`] and [`ChunkType`],
+//! behind the `serde` feature.
+//!
+//! Chunk types are written as strings and chunk data as hex. Chunks with a
+//! typed view that reproduces them byte for byte (IHDR, tEXt, zTXt, iTXt,
+//! pHYs and tIME) are written decoded instead, so a description can be
+//! edited and turned back into a file:
+//!
+//! ```json
+//! {
+//!   "chunks": [
+//!     { "type": "IHDR", "ihdr": { "width": 1, "height": 1, ... } },
+//!     { "type": "tEXt", "text": { "keyword": "Author", "text": "pngme" } },
+//!     { "type": "IDAT", "data": "789c6300010000050001" },
+//!     { "type": "IEND", "data": "" }
+//!   ],
+//!   "trailer": "6869"
+//! }
+//! ```
+//!
+//! When reading, `type` may be left out of decoded chunks, `data` may be
+//! left out of empty chunks, and `trailer` may be left out when there is no
+//! data after IEND. Lengths and crcs are always computed.
+
+use std::borrow::Cow;
+use std::path::Path;
+#[cfg(feature = "manifest")]
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::chunks::ihdr::Ihdr;
+use crate::chunks::phys::Phys;
+use crate::chunks::text::Text;
+use crate::chunks::time::Time;
+use crate::hexdump::{from_hex, to_hex};
+use crate::png::Png;
+
+impl Serialize for ChunkType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ChunkType::from_str(&name).map_err(D::Error::custom)
+    }
+}
+
+/// Bytes written as a hex string.
+struct Hex<'a>(Cow<'a, [u8]>);
+
+impl Serialize for Hex<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Hex<'_> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        from_hex(&hex)
+            .map(|bytes| Hex(Cow::Owned(bytes)))
+            .map_err(D::Error::custom)
+    }
+}
+
+/// A chunk as it is written, and as it is read back from descriptions and
+/// manifests.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ChunkForm<'a> {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    chunk_type: Option<ChunkType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Hex<'a>>,
+    #[cfg(feature = "manifest")]
+    #[serde(default, skip_serializing)]
+    base64: Option<String>,
+    /// A file holding the data, only read from manifests.
+    #[cfg(feature = "manifest")]
+    #[serde(default, skip_serializing)]
+    file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ihdr: Option<Ihdr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<Text>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phys: Option<Phys>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time: Option<Time>,
+}
+
+/// The typed view of `chunk`, if it turns back into the very same chunk.
+fn exact<'a, T>(chunk: &'a Chunk, to_chunk: fn(&T) -> crate::Result<Chunk>) -> Option<T>
+where
+    T: TryFrom<&'a Chunk>,
+{
+    T::try_from(chunk)
+        .ok()
+        .filter(|view| to_chunk(view).is_ok_and(|encoded| encoded == *chunk))
+}
+
+impl<'a> ChunkForm<'a> {
+    fn new(chunk: &'a Chunk) -> ChunkForm<'a> {
+        let mut form = ChunkForm {
+            chunk_type: Some(*chunk.chunk_type()),
+            ..ChunkForm::default()
+        };
+        match &chunk.chunk_type().bytes() {
+            b"IHDR" => form.ihdr = exact(chunk, |ihdr| Ok(Ihdr::to_chunk(ihdr))),
+            b"tEXt" | b"zTXt" | b"iTXt" => form.text = exact(chunk, Text::to_chunk),
+            b"pHYs" => form.phys = exact(chunk, |phys| Ok(Phys::to_chunk(phys))),
+            b"tIME" => form.time = exact(chunk, |time| Ok(Time::to_chunk(time))),
+            _ => {}
+        }
+        if form.ihdr.is_none() && form.text.is_none() && form.phys.is_none() && form.time.is_none()
+        {
+            form.data = Some(Hex(Cow::Borrowed(chunk.data())));
+        }
+        form
+    }
+
+    fn decoded(&self) -> crate::Result<Vec<Chunk>> {
+        [
+            self.ihdr.as_ref().map(|ihdr| Ok(ihdr.to_chunk())),
+            self.text.as_ref().map(Text::to_chunk),
+            self.phys.as_ref().map(|phys| Ok(phys.to_chunk())),
+            self.time.as_ref().map(|time| Ok(time.to_chunk())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// The raw data given for the chunk, in any of the ways there are.
+    fn raw_data(&self, base_dir: Option<&Path>) -> crate::Result<Vec<Vec<u8>>> {
+        let mut data: Vec<Vec<u8>> = self.data.iter().map(|Hex(data)| data.to_vec()).collect();
+        data.extend(self.manifest_data(base_dir)?);
+        Ok(data)
+    }
+
+    /// The data given in the ways only manifests use.
+    #[cfg(feature = "manifest")]
+    fn manifest_data(&self, base_dir: Option<&Path>) -> crate::Result<Vec<Vec<u8>>> {
+        use base64::Engine;
+
+        let mut data = Vec::new();
+        if let Some(base64) = &self.base64 {
+            data.push(base64::engine::general_purpose::STANDARD.decode(base64.trim())?);
+        }
+        if let Some(file) = &self.file {
+            let base_dir = base_dir.ok_or("chunk data is only read from files in manifests")?;
+            let path = base_dir.join(file);
+            let bytes = std::fs::read(&path)
+                .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+            data.push(bytes);
+        }
+        Ok(data)
+    }
+
+    #[cfg(not(feature = "manifest"))]
+    fn manifest_data(&self, _base_dir: Option<&Path>) -> crate::Result<Vec<Vec<u8>>> {
+        Ok(Vec::new())
+    }
+
+    /// Builds the chunk, reading data files relative to `base_dir`.
+    pub(crate) fn into_chunk(self, base_dir: Option<&Path>) -> crate::Result<Chunk> {
+        let chunk = match (
+            self.raw_data(base_dir)?.as_mut_slice(),
+            self.decoded()?.as_slice(),
+        ) {
+            (data, []) if data.len() <= 1 => {
+                let chunk_type = self.chunk_type.ok_or("chunk without a type")?;
+                let data = data.first_mut().map(std::mem::take).unwrap_or_default();
+                return Ok(Chunk::new(chunk_type, data));
+            }
+            ([], [chunk]) => chunk.clone(),
+            _ => return Err("chunk data is given more than once".into()),
+        };
+        match self.chunk_type {
+            Some(chunk_type) if chunk_type != *chunk.chunk_type() => {
+                Err(format!("{chunk_type} chunk described as {}", chunk.chunk_type()).into())
+            }
+            _ => Ok(chunk),
+        }
+    }
+}
+
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChunkForm::new(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ChunkForm::deserialize(deserializer)?
+            .into_chunk(None)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Writes the chunks that [`Png::as_bytes`] would write.
+impl Serialize for Png {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let chunks: Vec<&Chunk> = self.kept_chunks().collect();
+        let fields = if self.trailer().is_empty() { 1 } else { 2 };
+        let mut png = serializer.serialize_struct("Png", fields)?;
+        png.serialize_field("chunks", &chunks)?;
+        if self.trailer().is_empty() {
+            png.skip_field("trailer")?;
+        } else {
+            png.serialize_field("trailer", &Hex(Cow::Borrowed(self.trailer())))?;
+        }
+        png.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PngForm {
+    chunks: Vec<Chunk>,
+    #[serde(default)]
+    trailer: Option<Hex<'static>>,
+}
+
+impl<'de> Deserialize<'de> for Png {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let form = PngForm::deserialize(deserializer)?;
+        let mut png = Png::from_chunks(form.chunks);
+        if let Some(Hex(trailer)) = form.trailer {
+            png.set_trailer(trailer.into_owned());
+        }
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::ihdr::ColorType;
+    use crate::chunks::text::deflate;
+
+    fn testing_png() -> Png {
+        let ihdr = Ihdr::new(1, 1, 8, ColorType::Grayscale);
+        let mut png = Png::from_chunks(vec![
+            ihdr.to_chunk(),
+            Text::new("Author", "pngme").to_chunk().unwrap(),
+            Phys::from_dpi(72).unwrap().to_chunk(),
+            Chunk::new(ChunkType::IDAT, deflate(&[0, 0])),
+            Time::new(2024, 2, 29, 12, 0, 0).unwrap().to_chunk(),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"secret".to_vec()),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ]);
+        png.set_trailer(b"hi".to_vec());
+        png
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let png = testing_png();
+        let json = serde_json::to_string(&png).unwrap();
+        let parsed: Png = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_human_readable_form() {
+        let json = serde_json::to_value(testing_png()).unwrap();
+        let chunks = json["chunks"].as_array().unwrap();
+        assert_eq!(chunks[0]["ihdr"]["color_type"], "Grayscale");
+        assert_eq!(
+            chunks[1],
+            serde_json::json!({
+                "type": "tEXt",
+                "text": { "keyword": "Author", "text": "pngme", "compressed": false }
+            })
+        );
+        assert_eq!(chunks[2]["phys"]["unit"], 1);
+        assert_eq!(chunks[4]["time"]["year"], 2024);
+        assert_eq!(chunks[5]["data"], "736563726574");
+        assert_eq!(chunks[6], serde_json::json!({ "type": "IEND", "data": "" }));
+        assert_eq!(json["trailer"], "6869");
+    }
+
+    #[test]
+    fn test_edited_description() {
+        let json = r#"{
+            "chunks": [
+                { "text": { "keyword": "Title", "text": "edited" } },
+                { "type": "zTXt", "text": { "keyword": "Title", "text": "edited", "compressed": true } },
+                { "type": "ruSt", "data": "00 FF" },
+                { "type": "IEND", "data": "" }
+            ]
+        }"#;
+        let png: Png = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            png.chunks()[0],
+            Text::new("Title", "edited").to_chunk().unwrap()
+        );
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "zTXt");
+        assert_eq!(png.chunks()[2].data(), &[0, 0xff]);
+        assert!(png.trailer().is_empty());
+        let empty: Chunk = serde_json::from_str(r#"{ "type": "IEND" }"#).unwrap();
+        assert_eq!(empty, Chunk::new(ChunkType::IEND, Vec::new()));
+
+        for (json, error) in [
+            (r#"{ "data": "00" }"#, "chunk without a type"),
+            (r#"{ "type": "ruSt", "data": "0" }"#, "odd number"),
+            (r#"{ "type": "ru1t", "data": "" }"#, "non-letter"),
+            (
+                r#"{ "type": "iTXt", "text": { "keyword": "a", "text": "b" } }"#,
+                "iTXt chunk described as tEXt",
+            ),
+            (
+                r#"{ "text": { "keyword": "Title", "text": "✓" } }"#,
+                "not a Latin-1 character",
+            ),
+            (
+                r#"{ "data": "", "time": { "year": 2024, "month": 1, "day": 1, "hour": 0, "minute": 0, "second": 0 } }"#,
+                "given more than once",
+            ),
+            (
+                r#"{ "type": "IEND", "data": "", "crc": 0 }"#,
+                "unknown field",
+            ),
+        ] {
+            let message = serde_json::from_str::<Chunk>(json).unwrap_err().to_string();
+            assert!(message.contains(error), "{json}: {message}");
+        }
+    }
+
+    #[test]
+    fn test_inexact_chunks_stay_raw() {
+        // a valid IHDR followed by a byte the typed view would drop
+        let mut data = testing_png().chunks()[0].data().to_vec();
+        data.push(0);
+        let chunk = Chunk::new(ChunkType::IHDR, data);
+        let json = serde_json::to_value(&chunk).unwrap();
+        assert!(json.get("ihdr").is_none());
+        assert_eq!(serde_json::from_value::<Chunk>(json).unwrap(), chunk);
+    }
+}
@@ -0,0 +1,179 @@
+//! Removal of ancillary chunks by policy, for scrubbing metadata and
+//! shrinking files.
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::registry::Registry;
+use crate::{Error, Result};
+
+/// Which ancillary chunks to remove. Critical chunks are never touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Every ancillary chunk.
+    AllAncillary,
+    /// Ancillary chunks with a private type, that is with the public bit
+    /// of the chunk type clear.
+    Private,
+    /// Unknown ancillary chunks that are not safe to copy after the image
+    /// data has been edited. Chunks in the registry are kept, as
+    /// `Png::as_bytes` keeps them.
+    UnsafeToCopy,
+}
+
+impl Policy {
+    fn matches(&self, registry: &Registry, chunk_type: &ChunkType) -> bool {
+        match self {
+            Policy::AllAncillary => true,
+            Policy::Private => !chunk_type.is_public(),
+            Policy::UnsafeToCopy => !chunk_type.is_safe_to_copy() && !registry.contains(chunk_type),
+        }
+    }
+}
+
+/// A set of rules for [`Strip::apply`]. A chunk is removed when it is
+/// ancillary, not kept and either matches the policy or is on the drop list.
+#[derive(Debug, Clone, Default)]
+pub struct Strip {
+    pub policy: Option<Policy>,
+    pub keep: Vec<ChunkType>,
+    pub drop: Vec<ChunkType>,
+}
+
+impl Strip {
+    pub fn new(
+        policy: Option<Policy>,
+        keep: Vec<ChunkType>,
+        drop: Vec<ChunkType>,
+    ) -> Result<Strip> {
+        if let Some(critical) = drop.iter().find(|chunk_type| chunk_type.is_critical()) {
+            return Err(Error::from(format!(
+                "{critical} is a critical chunk and cannot be dropped"
+            )));
+        }
+        Ok(Strip { policy, keep, drop })
+    }
+
+    /// Whether a chunk is removed, with `registry` telling known chunk
+    /// types from unknown ones.
+    pub fn removes(&self, registry: &Registry, chunk_type: &ChunkType) -> bool {
+        if chunk_type.is_critical() || self.keep.contains(chunk_type) {
+            return false;
+        }
+        self.drop.contains(chunk_type)
+            || self
+                .policy
+                .is_some_and(|policy| policy.matches(registry, chunk_type))
+    }
+
+    /// Removes the matching chunks, returning them in file order. Known
+    /// chunk types come from [`Png::registry`].
+    pub fn apply(&self, png: &mut Png) -> Vec<Chunk> {
+        let registry = png.registry().clone();
+        png.remove_chunks(|chunk| self.removes(&registry, chunk.chunk_type()))
+    }
+}
+
+/// Bytes a list of chunks takes up in a file.
+pub fn file_size(chunks: &[Chunk]) -> usize {
+    chunks
+        .iter()
+        .map(|chunk| chunk.length() as usize + 12)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ChunkInfo;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        let chunk =
+            |chunk_type: &str| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), vec![0; 4]);
+        Png::from_chunks(vec![
+            chunk("IHDR"),
+            chunk("gAMA"),
+            chunk("pHYs"),
+            chunk("prVt"),
+            chunk("unSF"),
+            chunk("IDAT"),
+            chunk("tEXt"),
+            chunk("IEND"),
+        ])
+    }
+
+    fn types(chunks: &[Chunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    fn chunk_types(names: &[&str]) -> Vec<ChunkType> {
+        names
+            .iter()
+            .map(|name| ChunkType::from_str(name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_policies() {
+        let cases = [
+            (
+                Policy::AllAncillary,
+                vec!["gAMA", "pHYs", "prVt", "unSF", "tEXt"],
+            ),
+            (Policy::Private, vec!["prVt", "unSF"]),
+            // gAMA is unsafe to copy but registered
+            (Policy::UnsafeToCopy, vec!["unSF"]),
+        ];
+        for (policy, expected) in cases {
+            let mut png = testing_png();
+            let removed = Strip::new(Some(policy), Vec::new(), Vec::new())
+                .unwrap()
+                .apply(&mut png);
+            assert_eq!(types(&removed), expected, "{policy:?}");
+            assert_eq!(png.chunks().len(), 8 - expected.len());
+        }
+    }
+
+    #[test]
+    fn test_keep_and_drop() {
+        let mut png = testing_png();
+        let strip = Strip::new(
+            Some(Policy::AllAncillary),
+            chunk_types(&["pHYs", "tEXt"]),
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(types(&strip.apply(&mut png)), ["gAMA", "prVt", "unSF"]);
+
+        let mut png = testing_png();
+        let strip = Strip::new(None, Vec::new(), chunk_types(&["tEXt", "gAMA"])).unwrap();
+        let removed = strip.apply(&mut png);
+        assert_eq!(types(&removed), ["gAMA", "tEXt"]);
+        assert_eq!(file_size(&removed), 32);
+    }
+
+    #[test]
+    fn test_critical_chunks_stay() {
+        assert!(Strip::new(None, Vec::new(), chunk_types(&["PLTE"])).is_err());
+
+        let strip = Strip::new(Some(Policy::UnsafeToCopy), Vec::new(), Vec::new()).unwrap();
+        assert!(!strip.removes(crate::registry::standard(), &ChunkType::IDAT));
+    }
+
+    #[test]
+    fn test_registered_chunk_kept() {
+        let mut registry = Registry::default();
+        registry
+            .register(ChunkInfo::new(ChunkType::from_str("unSF").unwrap(), "Mine"))
+            .unwrap();
+        let mut png = testing_png();
+        png.set_registry(registry);
+
+        let strip = Strip::new(Some(Policy::UnsafeToCopy), Vec::new(), Vec::new()).unwrap();
+        assert!(strip.apply(&mut png).is_empty());
+    }
+}